@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::phase::Phase;
+use super::Target;
+use crate::effects::Effect;
+
+// One step of battle resolution. Card plays and turn ends only queue these,
+// the queue is worked through one action at a time so each step can be shown
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    Effect(Effect, Target),
+    EnterPhase(Phase),
+    // poison and block expiry at the start of the enemy's turn
+    EnemyTurnStart(usize),
+    EnemyMove(usize),
+    // the remaining hits of a multi attack
+    EnemyHit(usize, i8),
+    EnemyTurnEnd(usize),
+    // the last of a played card's effects has resolved
+    CardResolved,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionQueue(VecDeque<Action>);
+
+impl ActionQueue {
+    pub fn push(&mut self, action: Action) {
+        self.0.push_back(action);
+    }
+
+    // Runs before everything already queued, for actions that expand into more
+    pub fn push_front(&mut self, action: Action) {
+        self.0.push_front(action);
+    }
+
+    pub fn pop(&mut self) -> Option<Action> {
+        self.0.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, action: &Action) -> bool {
+        self.0.contains(action)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
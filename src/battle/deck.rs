@@ -0,0 +1,104 @@
+use crate::rng::{RunRng, Stream};
+use crate::CardID;
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_HAND_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Deck {
+    pub draw_pile: Vec<CardID>,
+    pub hand: Vec<CardID>,
+    pub discard: Vec<CardID>,
+}
+
+impl Deck {
+    pub fn new(cards: Vec<CardID>) -> Self {
+        Deck {
+            draw_pile: cards,
+            hand: Vec::new(),
+            discard: Vec::new(),
+        }
+    }
+
+    pub fn shuffle(&mut self, rng: &mut RunRng) {
+        self.draw_pile.shuffle(rng.stream(Stream::Deck));
+    }
+
+    // Moves the discard pile back under the draw pile once it runs dry, stops
+    // early when both piles are empty or the hand is full
+    pub fn draw(&mut self, count: usize, rng: &mut RunRng) -> Vec<CardID> {
+        let mut drawn = Vec::new();
+        for _ in 0..count {
+            if self.hand.len() >= MAX_HAND_SIZE {
+                break;
+            }
+            if self.draw_pile.is_empty() {
+                if self.discard.is_empty() {
+                    break;
+                }
+                self.draw_pile.append(&mut self.discard);
+                self.shuffle(rng);
+            }
+            if let Some(card_id) = self.draw_pile.pop() {
+                self.hand.push(card_id.clone());
+                drawn.push(card_id);
+            }
+        }
+        drawn
+    }
+
+    pub fn take_from_hand(&mut self, card_id: &str) -> Option<CardID> {
+        let index = self.hand.iter().position(|id| *id == card_id)?;
+        Some(self.hand.remove(index))
+    }
+
+    pub fn discard_hand(&mut self) {
+        self.discard.append(&mut self.hand);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deck(count: usize) -> Deck {
+        Deck::new((0..count).map(|index| index.to_string()).collect())
+    }
+
+    fn total(deck: &Deck) -> usize {
+        deck.draw_pile.len() + deck.hand.len() + deck.discard.len()
+    }
+
+    #[test]
+    fn the_discard_pile_is_reshuffled_once_the_draw_pile_runs_out() {
+        let mut rng = RunRng::new(1);
+        let mut deck = deck(7);
+        assert_eq!(deck.draw(5, &mut rng).len(), 5);
+        deck.discard_hand();
+        assert_eq!((deck.draw_pile.len(), deck.discard.len()), (2, 5));
+
+        assert_eq!(deck.draw(5, &mut rng).len(), 5);
+        assert_eq!((deck.draw_pile.len(), deck.discard.len()), (2, 0));
+        assert_eq!(total(&deck), 7);
+    }
+
+    #[test]
+    fn drawing_stops_when_every_card_is_in_hand() {
+        let mut rng = RunRng::new(1);
+        let mut deck = deck(3);
+        assert_eq!(deck.draw(5, &mut rng).len(), 3);
+        assert_eq!(deck.hand.len(), 3);
+        assert_eq!(total(&deck), 3);
+    }
+
+    #[test]
+    fn the_hand_never_goes_over_the_limit() {
+        let mut rng = RunRng::new(1);
+        let mut deck = deck(MAX_HAND_SIZE + 5);
+        deck.draw(MAX_HAND_SIZE + 5, &mut rng);
+        assert_eq!(deck.hand.len(), MAX_HAND_SIZE);
+        assert_eq!(deck.draw_pile.len(), 5);
+    }
+}
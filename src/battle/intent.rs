@@ -0,0 +1,139 @@
+use crate::battle::status::Status;
+use crate::rng::{RunRng, Stream};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum MoveAction {
+    Attack(i8),
+    MultiAttack(i8, u8),
+    Defend(i8),
+    Buff(Status, i8),
+    Debuff(Status, i8),
+}
+
+fn default_max_in_a_row() -> usize {
+    2
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EnemyMove {
+    pub name: String,
+    pub weight: u32,
+    // "never the same move three times" is the default
+    #[serde(default = "default_max_in_a_row")]
+    pub max_in_a_row: usize,
+    pub action: MoveAction,
+}
+
+// What the player gets told about an enemy's next move, damage already
+// includes strength, weak and vulnerable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Intent {
+    Attack(i8),
+    MultiAttack(i8, u8),
+    Defend(i8),
+    Buff,
+    Debuff,
+}
+
+impl Intent {
+    pub fn describe(&self) -> String {
+        match self {
+            Intent::Attack(damage) => format!("Attack {}", damage),
+            Intent::MultiAttack(damage, hits) => format!("Attack {}x{}", damage, hits),
+            Intent::Defend(block) => format!("Block {}", block),
+            Intent::Buff => "Buff".to_string(),
+            Intent::Debuff => "Debuff".to_string(),
+        }
+    }
+}
+
+fn repeats_too_often(moves: &[EnemyMove], history: &[usize], index: usize) -> bool {
+    let limit = moves[index].max_in_a_row;
+    history.len() >= limit && history.iter().rev().take(limit).all(|used| *used == index)
+}
+
+// Weighted pick over the moves the history still allows. If the repeat rules
+// would leave nothing to choose from, e.g. an enemy with a single move, they
+// are ignored, moves with no weight are never picked
+pub fn choose_move(moves: &[EnemyMove], history: &[usize], rng: &mut RunRng) -> Option<usize> {
+    let weighted = (0..moves.len()).filter(|index| moves[*index].weight > 0);
+    let mut allowed: Vec<usize> = weighted
+        .clone()
+        .filter(|index| !repeats_too_often(moves, history, *index))
+        .collect();
+    if allowed.is_empty() {
+        allowed = weighted.collect();
+    }
+    let total: u32 = allowed.iter().map(|index| moves[*index].weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.stream(Stream::EnemyAi).gen_range(0, total);
+    for index in allowed {
+        let weight = moves[index].weight;
+        if roll < weight {
+            return Some(index);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enemy_move(weight: u32, max_in_a_row: usize) -> EnemyMove {
+        EnemyMove {
+            name: "Move".to_string(),
+            weight,
+            max_in_a_row,
+            action: MoveAction::Attack(1),
+        }
+    }
+
+    fn pick_many(moves: &[EnemyMove], count: usize) -> Vec<usize> {
+        let mut rng = RunRng::new(3);
+        let mut history = Vec::new();
+        for _ in 0..count {
+            let index = choose_move(moves, &history, &mut rng).unwrap();
+            history.push(index);
+        }
+        history
+    }
+
+    #[test]
+    fn no_move_is_used_three_times_in_a_row_by_default() {
+        let moves = [enemy_move(10, default_max_in_a_row()), enemy_move(1, default_max_in_a_row())];
+        let history = pick_many(&moves, 500);
+        assert!(history.windows(3).all(|picks| !(picks[0] == picks[1] && picks[1] == picks[2])));
+        assert!(history.windows(2).any(|picks| picks[0] == picks[1]));
+    }
+
+    #[test]
+    fn a_move_limited_to_one_in_a_row_never_repeats() {
+        // the dragon's roar
+        let moves = [enemy_move(2, 2), enemy_move(1, 1), enemy_move(2, 2)];
+        let history = pick_many(&moves, 500);
+        assert!(history.windows(2).all(|picks| !(picks[0] == 1 && picks[1] == 1)));
+        assert!(history.contains(&1));
+    }
+
+    #[test]
+    fn moves_are_picked_by_weight() {
+        let moves = [enemy_move(3, usize::MAX), enemy_move(1, usize::MAX)];
+        let history = pick_many(&moves, 4000);
+        let first = history.iter().filter(|index| **index == 0).count();
+        assert!((2800..3200).contains(&first), "picked the heavier move {} times", first);
+    }
+
+    #[test]
+    fn moves_without_weight_are_never_picked() {
+        let moves = [enemy_move(1, 1), enemy_move(0, 1)];
+        assert!(pick_many(&moves, 50).iter().all(|index| *index == 0));
+        assert_eq!(choose_move(&[enemy_move(0, 1)], &[], &mut RunRng::new(3)), None);
+    }
+}
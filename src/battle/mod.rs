@@ -0,0 +1,1088 @@
+// Battle rules with no rendering in them, the specs systems only issue commands
+// and mirror this state onto entities so a battle can run without a window.
+use crate::cards::Cost;
+use crate::enemies::{Enemy, Row};
+use crate::effects::{Effect, Trigger};
+use crate::rng::{RunRng, Stream};
+use crate::relics::Relic;
+use crate::{CardDB, CardID, EnemyID, RelicID};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub mod action;
+pub mod deck;
+pub mod intent;
+pub mod phase;
+pub mod status;
+
+use self::action::{Action, ActionQueue};
+use self::deck::Deck;
+use self::intent::{choose_move, EnemyMove, Intent, MoveAction};
+use self::phase::Phase;
+use self::status::{modify_damage, Statuses};
+
+pub const CARDS_PER_TURN: usize = 5;
+pub const STARTING_ENERGY: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    Enemy(usize),
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayError {
+    NotInHand,
+    UnknownCard,
+    InvalidTarget,
+    NotEnoughEnergy,
+    NotYourTurn,
+    BattleOver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Won,
+    Lost,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fighter {
+    pub max_health: i8,
+    pub health: i8,
+    pub block: i8,
+    pub statuses: Statuses,
+}
+
+impl Fighter {
+    pub fn new(health: i8) -> Self {
+        Fighter {
+            max_health: health,
+            health,
+            block: 0,
+            statuses: Statuses::default(),
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0
+    }
+
+    // Returns the health actually lost
+    pub fn take_damage(&mut self, amount: i8) -> i8 {
+        let blocked = std::cmp::min(self.block, amount);
+        self.block -= blocked;
+        self.lose_health(amount - blocked)
+    }
+
+    // Skips block, used for poison
+    pub fn lose_health(&mut self, amount: i8) -> i8 {
+        let lost = std::cmp::min(self.health, std::cmp::max(0, amount));
+        self.health -= lost;
+        lost
+    }
+
+    pub fn gain_block(&mut self, amount: i8) {
+        self.block = self.block.saturating_add(amount);
+    }
+
+    // Block lasts until the start of its owner's next turn, returns the
+    // health lost to poison
+    pub fn tick_turn_start(&mut self) -> i8 {
+        if !self.statuses.retain_block() {
+            self.block = 0;
+        }
+        let poison = self.statuses.tick_turn_start();
+        self.lose_health(poison)
+    }
+
+    pub fn heal(&mut self, amount: i8) {
+        self.health = std::cmp::min(self.max_health, self.health.saturating_add(amount));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleEnemy {
+    pub id: EnemyID,
+    pub name: String,
+    pub open: bool,
+    pub row: Row,
+    pub fighter: Fighter,
+    pub moves: Vec<EnemyMove>,
+    // indices into `moves`, oldest first
+    pub history: Vec<usize>,
+    pub next_move: Option<usize>,
+    // intent shown this turn without being revealed
+    pub peeked: bool,
+}
+
+impl BattleEnemy {
+    pub fn new(enemy: &Enemy, row: Row) -> Self {
+        BattleEnemy {
+            id: enemy.id.clone(),
+            name: enemy.name.clone(),
+            open: enemy.open,
+            row,
+            fighter: Fighter::new(enemy.health),
+            moves: enemy.moves.clone(),
+            history: Vec::new(),
+            next_move: None,
+            peeked: false,
+        }
+    }
+
+    pub fn intent_known(&self) -> bool {
+        self.open || self.peeked
+    }
+
+    pub fn intent(&self, player: &Fighter) -> Option<Intent> {
+        let action = self.moves.get(self.next_move?)?.action;
+        let damage = |base| modify_damage(base, &self.fighter.statuses, &player.statuses);
+        Some(match action {
+            MoveAction::Attack(base) => Intent::Attack(damage(base)),
+            MoveAction::MultiAttack(base, hits) => Intent::MultiAttack(damage(base), hits),
+            MoveAction::Defend(block) => Intent::Defend(block),
+            MoveAction::Buff(_, _) => Intent::Buff,
+            MoveAction::Debuff(_, _) => Intent::Debuff,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BattleStats {
+    pub damage_dealt: u32,
+    pub cards_played: u32,
+    // what last took health off the player, the cause of death if they lose
+    pub last_damage_source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Source {
+    Card(CardID),
+    Relic(RelicID),
+}
+
+// A subscription to a battle event, made when a card with triggers is played
+// and for every relic as the battle starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Listener {
+    pub source: Source,
+    pub trigger: Trigger,
+    pub effects: Vec<Effect>,
+    // fires when `counter` reaches `every`, then starts counting again
+    pub every: u32,
+    pub counter: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Battle {
+    pub player: Fighter,
+    pub enemies: Vec<BattleEnemy>,
+    pub deck: Deck,
+    pub energy: u8,
+    pub max_energy: u8,
+    pub turn: u32,
+    pub stats: BattleStats,
+    pub listeners: Vec<Listener>,
+    pub phase: Phase,
+    pub queue: ActionQueue,
+    // applied to the damage of the card being played, reset once it resolves
+    pub damage_multiplier: i8,
+    // relics that fired since the ui last looked, for flashing their icons
+    #[serde(skip)]
+    pub flashes: Vec<RelicID>,
+}
+
+impl Battle {
+    pub fn new(player: Fighter, deck: Vec<CardID>, enemies: Vec<BattleEnemy>) -> Self {
+        Battle {
+            player,
+            enemies,
+            deck: Deck::new(deck),
+            energy: 0,
+            max_energy: STARTING_ENERGY,
+            turn: 0,
+            stats: BattleStats::default(),
+            listeners: Vec::new(),
+            phase: Phase::BattleStart,
+            queue: ActionQueue::default(),
+            damage_multiplier: 1,
+            flashes: Vec::new(),
+        }
+    }
+
+    pub fn add_relic(&mut self, relic: &Relic, counter: u32) {
+        self.listeners.push(Listener {
+            source: Source::Relic(relic.id.clone()),
+            trigger: relic.trigger,
+            effects: relic.effects.clone(),
+            every: relic.every,
+            counter,
+        });
+    }
+
+    // Where each relic's counter got to, for the run to carry on
+    pub fn relic_counters(&self) -> impl Iterator<Item = (&RelicID, u32)> + '_ {
+        self.listeners.iter().filter_map(|listener| match &listener.source {
+            Source::Relic(id) => Some((id, listener.counter)),
+            Source::Card(_) => None,
+        })
+    }
+
+    pub fn start(&mut self) {
+        self.queue.push(Action::EnterPhase(Phase::BattleStart));
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.player.is_dead() {
+            Some(Outcome::Lost)
+        } else if self.enemies.iter().all(|enemy| enemy.fighter.is_dead()) {
+            Some(Outcome::Won)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_alive(&self, index: usize) -> bool {
+        self.enemies
+            .get(index)
+            .map(|enemy| !enemy.fighter.is_dead())
+            .unwrap_or(false)
+    }
+
+    pub fn play_card(
+        &mut self,
+        cards: &CardDB,
+        card_id: &str,
+        target: Target,
+    ) -> Result<(), PlayError> {
+        if self.outcome().is_some() {
+            return Err(PlayError::BattleOver);
+        }
+        if self.phase != Phase::PlayerMain || self.turn_ending() {
+            return Err(PlayError::NotYourTurn);
+        }
+        let card = cards.get(card_id).ok_or(PlayError::UnknownCard)?;
+        if !self.deck.hand.iter().any(|id| id == card_id) {
+            return Err(PlayError::NotInHand);
+        }
+        match target {
+            Target::Enemy(index) if !self.is_alive(index) => return Err(PlayError::InvalidTarget),
+            Target::None if needs_target(&card.effects) => return Err(PlayError::InvalidTarget),
+            _ => {}
+        }
+        let repeats = match card.cost {
+            Cost::Fixed(cost) if cost > self.energy => return Err(PlayError::NotEnoughEnergy),
+            Cost::Fixed(cost) => {
+                self.energy -= cost;
+                1
+            }
+            Cost::X => std::mem::replace(&mut self.energy, 0),
+        };
+
+        let card_id = self.deck.take_from_hand(card_id).ok_or(PlayError::NotInHand)?;
+        // emitted first so what it triggers resolves before the card, a
+        // relic can then change how the card itself plays out. Subscribing
+        // after the event keeps a card from triggering off being played
+        self.emit(Trigger::OnCardPlayed);
+        for _ in 0..repeats {
+            for effect in &card.effects {
+                self.queue.push(Action::Effect(effect.clone(), target));
+            }
+        }
+        self.queue.push(Action::CardResolved);
+        // The card's effects are only queued, so its listeners are in place
+        // before they resolve and do fire off them, Keen eye draws for its own
+        // reveal. Each play subscribes again, a card played twice fires its
+        // triggers twice for the rest of the battle
+        for trigger in &card.triggers {
+            self.listeners.push(Listener {
+                source: Source::Card(card_id.clone()),
+                trigger: trigger.trigger,
+                effects: trigger.effects.clone(),
+                every: 1,
+                counter: 0,
+            });
+        }
+        self.deck.discard.push(card_id);
+        self.stats.cards_played += 1;
+        Ok(())
+    }
+
+    // Ending the turn twice before it resolves does nothing
+    pub fn end_turn(&mut self) {
+        if self.outcome().is_some() || self.phase != Phase::PlayerMain || self.turn_ending() {
+            return;
+        }
+        self.queue.push(Action::EnterPhase(Phase::PlayerTurnEnd));
+    }
+
+    // The turn is still `PlayerMain` until the queue gets to ending it, no
+    // more cards can be played in the meantime
+    fn turn_ending(&self) -> bool {
+        self.queue.contains(&Action::EnterPhase(Phase::PlayerTurnEnd))
+    }
+
+    // Resolves the next queued action and returns it, once the battle is
+    // over the rest of the queue is dropped and it moves to `BattleEnd`
+    pub fn step(&mut self, rng: &mut RunRng) -> Option<Action> {
+        if self.outcome().is_some() {
+            self.queue.clear();
+            if self.phase != Phase::BattleEnd {
+                self.enter_phase(Phase::BattleEnd, rng);
+            }
+            return None;
+        }
+        let action = self.queue.pop()?;
+        self.resolve_action(&action, rng);
+        Some(action)
+    }
+
+    // Queues the effects of everything listening for `trigger`, they have no
+    // target so anything that needs one picks a random enemy
+    fn emit(&mut self, trigger: Trigger) {
+        for listener in self.listeners.iter_mut().filter(|listener| listener.trigger == trigger) {
+            listener.counter += 1;
+            if listener.counter < listener.every {
+                continue;
+            }
+            listener.counter = 0;
+            for effect in &listener.effects {
+                self.queue.push(Action::Effect(effect.clone(), Target::None));
+            }
+            if let Source::Relic(id) = &listener.source {
+                self.flashes.push(id.clone());
+            }
+        }
+    }
+
+    fn reveal(&mut self, index: usize) {
+        if !self.enemies[index].open {
+            self.enemies[index].open = true;
+            self.emit(Trigger::OnEnemyRevealed);
+        }
+    }
+
+    fn draw(&mut self, count: usize, rng: &mut RunRng) {
+        for _ in 0..count {
+            let reshuffles = self.deck.draw_pile.is_empty();
+            if self.deck.draw(1, rng).is_empty() {
+                break;
+            }
+            if reshuffles {
+                self.emit(Trigger::OnShuffle);
+            }
+            self.emit(Trigger::OnCardDrawn);
+        }
+    }
+
+    // Living enemies that have been revealed, what the per revealed effects count
+    pub fn revealed_count(&self) -> usize {
+        (0..self.enemies.len())
+            .filter(|index| self.is_alive(*index) && self.enemies[*index].open)
+            .count()
+    }
+
+    fn random_enemy(&self, rng: &mut RunRng) -> Option<usize> {
+        let alive: Vec<usize> = (0..self.enemies.len()).filter(|index| self.is_alive(*index)).collect();
+        if alive.is_empty() {
+            None
+        } else {
+            Some(alive[rng.stream(Stream::CardEffects).gen_range(0, alive.len())])
+        }
+    }
+
+    // For when nothing needs to watch each step, e.g. the tests
+    pub fn resolve_all(&mut self, rng: &mut RunRng) {
+        while self.step(rng).is_some() {}
+    }
+
+    pub fn intent(&self, index: usize) -> Option<Intent> {
+        self.enemies.get(index)?.intent(&self.player)
+    }
+
+    // The hook for each phase, run as it is entered. Each queues the next
+    // phase except `PlayerMain`, which waits for the player to end the turn
+    fn enter_phase(&mut self, phase: Phase, rng: &mut RunRng) {
+        self.phase = phase;
+        match phase {
+            Phase::BattleStart => {
+                self.deck.shuffle(rng);
+                self.queue.push(Action::EnterPhase(Phase::PlayerTurnStart));
+            }
+            Phase::PlayerTurnStart => {
+                self.start_turn(rng);
+                // poison can end the battle here, `step` then moves straight
+                // to `BattleEnd`
+                if self.outcome().is_some() {
+                    return;
+                }
+                // after the first turn's energy and draw so relics can add to them
+                if self.turn == 1 {
+                    self.emit(Trigger::OnBattleStart);
+                }
+                self.emit(Trigger::OnTurnStart);
+                self.queue.push(Action::EnterPhase(Phase::PlayerMain));
+            }
+            Phase::PlayerMain => {}
+            Phase::PlayerTurnEnd => {
+                self.emit(Trigger::OnTurnEnd);
+                self.deck.discard_hand();
+                self.player.statuses.tick_turn_end();
+                self.queue.push(Action::EnterPhase(Phase::EnemyTurn));
+            }
+            Phase::EnemyTurn => {
+                for index in 0..self.enemies.len() {
+                    if self.is_alive(index) {
+                        self.queue.push(Action::EnemyTurnStart(index));
+                        self.queue.push(Action::EnemyMove(index));
+                        self.queue.push(Action::EnemyTurnEnd(index));
+                    }
+                }
+                self.queue.push(Action::EnterPhase(Phase::PlayerTurnStart));
+            }
+            // the battle is already decided so these resolve straight away
+            // rather than waiting in the queue
+            Phase::BattleEnd => {
+                if self.outcome() == Some(Outcome::Won) {
+                    self.emit(Trigger::OnVictory);
+                    while let Some(action) = self.queue.pop() {
+                        self.resolve_action(&action, rng);
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_turn(&mut self, rng: &mut RunRng) {
+        self.turn += 1;
+        if self.player.tick_turn_start() > 0 {
+            self.stats.last_damage_source = Some("Poison".to_string());
+            self.emit(Trigger::OnDamageTaken);
+        }
+        if self.outcome().is_some() {
+            return;
+        }
+        self.energy = self.max_energy;
+        for enemy in self.enemies.iter_mut().filter(|enemy| !enemy.fighter.is_dead()) {
+            enemy.next_move = choose_move(&enemy.moves, &enemy.history, rng);
+            enemy.peeked = false;
+        }
+        self.draw(CARDS_PER_TURN, rng);
+    }
+
+    fn resolve_action(&mut self, action: &Action, rng: &mut RunRng) {
+        match action {
+            Action::Effect(effect, target) => self.resolve(effect, *target, rng),
+            Action::EnterPhase(phase) => self.enter_phase(*phase, rng),
+            Action::EnemyTurnStart(index) => {
+                if self.is_alive(*index) {
+                    let poison = self.enemies[*index].fighter.tick_turn_start();
+                    self.stats.damage_dealt += poison as u32;
+                }
+            }
+            Action::EnemyMove(index) => {
+                let index = *index;
+                if !self.is_alive(index) {
+                    return;
+                }
+                let enemy = &mut self.enemies[index];
+                if let Some(move_index) = enemy.next_move.take() {
+                    enemy.history.push(move_index);
+                    let action = enemy.moves[move_index].action;
+                    self.perform(index, action);
+                }
+            }
+            Action::EnemyHit(index, damage) => {
+                if self.is_alive(*index) {
+                    self.attack_player(*index, *damage);
+                }
+            }
+            Action::EnemyTurnEnd(index) => {
+                if self.is_alive(*index) {
+                    self.enemies[*index].fighter.statuses.tick_turn_end();
+                }
+            }
+            Action::CardResolved => self.damage_multiplier = 1,
+        }
+    }
+
+    fn perform(&mut self, index: usize, action: MoveAction) {
+        match action {
+            MoveAction::Attack(damage) => self.attack_player(index, damage),
+            // each hit after the first is its own action so they land one by one
+            MoveAction::MultiAttack(damage, hits) => {
+                for _ in 1..hits {
+                    self.queue.push_front(Action::EnemyHit(index, damage));
+                }
+                if hits > 0 {
+                    self.attack_player(index, damage);
+                }
+            }
+            MoveAction::Defend(block) => self.enemies[index].fighter.gain_block(block),
+            MoveAction::Buff(status, stacks) => self.enemies[index].fighter.statuses.apply(status, stacks),
+            MoveAction::Debuff(status, stacks) => self.player.statuses.apply(status, stacks),
+        }
+    }
+
+    fn attack_player(&mut self, enemy: usize, base: i8) {
+        let damage = modify_damage(base, &self.enemies[enemy].fighter.statuses, &self.player.statuses);
+        let attacker = &self.enemies[enemy];
+        if self.player.take_damage(damage) > 0 {
+            // the move being made is always the last one in the history
+            let source = match attacker.history.last() {
+                Some(&move_index) => format!("{}'s {}", attacker.name, attacker.moves[move_index].name),
+                None => attacker.name.clone(),
+            };
+            self.stats.last_damage_source = Some(source);
+            self.emit(Trigger::OnDamageTaken);
+        }
+    }
+
+    // Hitting a hidden enemy reveals it
+    fn attack_enemy(&mut self, enemy: usize, base: i8) {
+        self.reveal(enemy);
+        let damage = modify_damage(base, &self.player.statuses, &self.enemies[enemy].fighter.statuses)
+            .saturating_mul(self.damage_multiplier);
+        let lost = self.enemies[enemy].fighter.take_damage(damage);
+        self.stats.damage_dealt += lost as u32;
+        if lost > 0 {
+            self.emit(Trigger::OnDamageDealt);
+        }
+    }
+
+    fn resolve(&mut self, effect: &Effect, target: Target, rng: &mut RunRng) {
+        let target = match target {
+            Target::None if needs_target(std::slice::from_ref(effect)) => {
+                self.random_enemy(rng).map_or(Target::None, Target::Enemy)
+            }
+            target => target,
+        };
+        match effect {
+            Effect::DealDamage(amount) => {
+                if let Target::Enemy(index) = target {
+                    self.attack_enemy(index, *amount);
+                }
+            }
+            Effect::DealDamagePerRevealed(amount) => {
+                if let Target::Enemy(index) = target {
+                    // the enemy being hit counts too
+                    self.reveal(index);
+                    let revealed = self.revealed_count() as i8;
+                    self.attack_enemy(index, amount.saturating_mul(revealed));
+                }
+            }
+            Effect::Draw(count) => self.draw(*count as usize, rng),
+            Effect::FlipRandomEnemy => {
+                let hidden: Vec<usize> = self
+                    .enemies
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, enemy)| !enemy.open && !enemy.fighter.is_dead())
+                    .map(|(index, _)| index)
+                    .collect();
+                if !hidden.is_empty() {
+                    let index = hidden[rng.stream(Stream::CardEffects).gen_range(0, hidden.len())];
+                    self.reveal(index);
+                }
+            }
+            Effect::Reveal => {
+                if let Target::Enemy(index) = target {
+                    self.reveal(index);
+                }
+            }
+            Effect::Peek => {
+                if let Target::Enemy(index) = target {
+                    self.enemies[index].peeked = true;
+                }
+            }
+            Effect::Heal(amount) => self.player.heal(*amount),
+            Effect::GainBlock(amount) => self.player.gain_block(*amount),
+            Effect::GainEnergy(amount) => self.energy = self.energy.saturating_add(*amount),
+            Effect::GainBlockPerRevealed(amount) => {
+                let revealed = self.revealed_count() as i8;
+                self.player.gain_block(amount.saturating_mul(revealed));
+            }
+            Effect::ApplyStatus(status, stacks) => {
+                if let Target::Enemy(index) = target {
+                    self.enemies[index].fighter.statuses.apply(*status, *stacks);
+                }
+            }
+            Effect::GainStatus(status, stacks) => self.player.statuses.apply(*status, *stacks),
+            Effect::DoubleDamage => self.damage_multiplier = self.damage_multiplier.saturating_mul(2),
+        }
+    }
+}
+
+pub fn needs_target(effects: &[Effect]) -> bool {
+    effects
+        .iter()
+        .any(|effect| {
+            matches!(
+                effect,
+                Effect::DealDamage(_)
+                    | Effect::DealDamagePerRevealed(_)
+                    | Effect::Reveal
+                    | Effect::Peek
+                    | Effect::ApplyStatus(_, _)
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::status::Status;
+    use super::*;
+    use crate::cards::Card;
+    use crate::effects::TriggeredEffect;
+
+    fn card(id: &str, cost: u8, effects: Vec<Effect>) -> (CardID, Card) {
+        let card = Card {
+            id: id.to_string(),
+            name: id.to_string(),
+            cost: Cost::Fixed(cost),
+            effects,
+            ..Card::default()
+        };
+        (id.to_string(), card)
+    }
+
+    fn cards() -> CardDB {
+        vec![
+            card("strike", 1, vec![Effect::DealDamage(6)]),
+            card("defend", 1, vec![Effect::GainBlock(5)]),
+            card("heavy", 4, vec![Effect::DealDamage(20)]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn keen_eye() -> (CardID, Card) {
+        let (id, mut card) = card("keen_eye", 1, vec![Effect::FlipRandomEnemy]);
+        card.triggers = vec![TriggeredEffect {
+            trigger: Trigger::OnEnemyRevealed,
+            effects: vec![Effect::Draw(1)],
+        }];
+        (id, card)
+    }
+
+    fn enemy(action: MoveAction) -> Enemy {
+        Enemy {
+            id: "dummy".to_string(),
+            name: "Dummy".to_string(),
+            health: 30,
+            open: true,
+            moves: vec![EnemyMove {
+                name: "Move".to_string(),
+                weight: 1,
+                max_in_a_row: usize::MAX,
+                action,
+            }],
+        }
+    }
+
+    // A battle started and resolved up to the player's first turn
+    fn start_battle(deck: &[&str], enemies: &[Enemy]) -> (Battle, RunRng) {
+        let deck = deck.iter().map(|id| id.to_string()).collect();
+        let enemies = enemies.iter().map(|enemy| BattleEnemy::new(enemy, Row::Front)).collect();
+        let mut battle = Battle::new(Fighter::new(20), deck, enemies);
+        let mut rng = RunRng::new(1);
+        battle.start();
+        battle.resolve_all(&mut rng);
+        (battle, rng)
+    }
+
+    // Steps until the battle waits for the player, returning each phase entered
+    fn phases_entered(battle: &mut Battle, rng: &mut RunRng) -> Vec<Phase> {
+        let mut phases = Vec::new();
+        while let Some(action) = battle.step(rng) {
+            if let Action::EnterPhase(phase) = action {
+                phases.push(phase);
+            }
+        }
+        phases
+    }
+
+    #[test]
+    fn turns_go_through_each_phase_in_order() {
+        let enemies = [enemy(MoveAction::Attack(5))];
+        let deck = vec!["strike".to_string(); 10];
+        let enemies = enemies.iter().map(|enemy| BattleEnemy::new(enemy, Row::Front)).collect();
+        let mut battle = Battle::new(Fighter::new(20), deck, enemies);
+        let mut rng = RunRng::new(1);
+        battle.start();
+        assert_eq!(
+            phases_entered(&mut battle, &mut rng),
+            vec![Phase::BattleStart, Phase::PlayerTurnStart, Phase::PlayerMain]
+        );
+
+        battle.end_turn();
+        assert_eq!(
+            phases_entered(&mut battle, &mut rng),
+            vec![Phase::PlayerTurnEnd, Phase::EnemyTurn, Phase::PlayerTurnStart, Phase::PlayerMain]
+        );
+    }
+
+    #[test]
+    fn the_battle_ends_as_soon_as_the_player_dies() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 10], &[enemy(MoveAction::Attack(30))]);
+        battle.end_turn();
+        assert_eq!(
+            phases_entered(&mut battle, &mut rng),
+            vec![Phase::PlayerTurnEnd, Phase::EnemyTurn]
+        );
+        assert_eq!(battle.outcome(), Some(Outcome::Lost));
+        assert_eq!(battle.phase, Phase::BattleEnd);
+        assert!(battle.queue.is_empty());
+        battle.end_turn();
+        assert!(battle.queue.is_empty());
+    }
+
+    #[test]
+    fn poison_at_turn_start_ends_the_battle_before_the_turn_begins() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 10], &[enemy(MoveAction::Defend(5))]);
+        battle.add_relic(
+            &Relic {
+                id: "drum".to_string(),
+                name: "Drum".to_string(),
+                color: (0, 0, 0),
+                trigger: Trigger::OnTurnStart,
+                every: 1,
+                effects: vec![Effect::GainBlock(3)],
+            },
+            0,
+        );
+        battle.player.health = 3;
+        battle.player.statuses.apply(Status::Poison, 5);
+        battle.end_turn();
+        assert_eq!(
+            phases_entered(&mut battle, &mut rng),
+            vec![Phase::PlayerTurnEnd, Phase::EnemyTurn, Phase::PlayerTurnStart]
+        );
+        assert_eq!(battle.outcome(), Some(Outcome::Lost));
+        assert_eq!(battle.phase, Phase::BattleEnd);
+        assert!(battle.flashes.is_empty());
+        assert_eq!(battle.player.block, 0);
+        assert_eq!(battle.stats.last_damage_source.as_deref(), Some("Poison"));
+    }
+
+    #[test]
+    fn playing_a_card_spends_energy_and_resolves_its_effects() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 5], &[enemy(MoveAction::Attack(5))]);
+        assert_eq!(battle.energy, STARTING_ENERGY);
+
+        battle.play_card(&cards(), "strike", Target::Enemy(0)).unwrap();
+        assert_eq!(battle.energy, STARTING_ENERGY - 1);
+        assert_eq!(battle.deck.hand.len(), 4);
+        assert_eq!(battle.deck.discard, vec!["strike".to_string()]);
+
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.enemies[0].fighter.health, 24);
+        assert_eq!(battle.stats.cards_played, 1);
+        assert_eq!(battle.stats.damage_dealt, 6);
+    }
+
+    #[test]
+    fn cards_cost_more_than_the_energy_left_are_not_played() {
+        let (mut battle, _) = start_battle(&["heavy"; 5], &[enemy(MoveAction::Attack(5))]);
+        let result = battle.play_card(&cards(), "heavy", Target::Enemy(0));
+        assert_eq!(result, Err(PlayError::NotEnoughEnergy));
+        assert_eq!(battle.energy, STARTING_ENERGY);
+        assert_eq!(battle.deck.hand.len(), 5);
+    }
+
+    #[test]
+    fn dead_and_missing_enemies_cannot_be_targeted() {
+        let enemies = [enemy(MoveAction::Attack(5)), enemy(MoveAction::Attack(5))];
+        let (mut battle, _) = start_battle(&["strike"; 5], &enemies);
+        battle.enemies[0].fighter.health = 0;
+
+        let cards = cards();
+        assert_eq!(battle.play_card(&cards, "strike", Target::Enemy(0)), Err(PlayError::InvalidTarget));
+        assert_eq!(battle.play_card(&cards, "strike", Target::Enemy(2)), Err(PlayError::InvalidTarget));
+        assert_eq!(battle.play_card(&cards, "strike", Target::None), Err(PlayError::InvalidTarget));
+        assert_eq!(battle.energy, STARTING_ENERGY);
+        assert_eq!(battle.deck.hand.len(), 5);
+        assert_eq!(battle.play_card(&cards, "strike", Target::Enemy(1)), Ok(()));
+    }
+
+    #[test]
+    fn untargeted_cards_play_without_an_enemy() {
+        let (mut battle, mut rng) = start_battle(&["defend"; 5], &[enemy(MoveAction::Attack(5))]);
+        battle.play_card(&cards(), "defend", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.player.block, 5);
+    }
+
+    #[test]
+    fn ending_the_turn_runs_the_enemy_turn_and_starts_the_next() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 10], &[enemy(MoveAction::Attack(5))]);
+        battle.play_card(&cards(), "strike", Target::Enemy(0)).unwrap();
+        battle.end_turn();
+        // a second end turn before the first resolves is ignored
+        battle.end_turn();
+        battle.resolve_all(&mut rng);
+
+        assert!(battle.queue.is_empty());
+        assert_eq!(battle.turn, 2);
+        assert_eq!(battle.phase, Phase::PlayerMain);
+        assert_eq!(battle.player.health, 15);
+        assert_eq!(battle.energy, STARTING_ENERGY);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN);
+        assert_eq!(battle.deck.discard.len(), 5);
+        assert_eq!(battle.enemies[0].history, vec![0]);
+    }
+
+    #[test]
+    fn cards_cannot_be_played_once_the_turn_is_ending() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 10], &[enemy(MoveAction::Attack(5))]);
+        battle.end_turn();
+        assert_eq!(battle.phase, Phase::PlayerMain);
+        let result = battle.play_card(&cards(), "strike", Target::Enemy(0));
+        assert_eq!(result, Err(PlayError::NotYourTurn));
+        assert_eq!(battle.energy, STARTING_ENERGY);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN);
+
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.play_card(&cards(), "strike", Target::Enemy(0)), Ok(()));
+    }
+
+    #[test]
+    fn block_soaks_up_damage_until_its_owners_next_turn() {
+        let (mut battle, mut rng) = start_battle(&["defend"; 10], &[enemy(MoveAction::Attack(8))]);
+        battle.play_card(&cards(), "defend", Target::None).unwrap();
+        battle.end_turn();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.player.health, 17);
+        assert_eq!(battle.player.block, 0);
+    }
+
+    #[test]
+    fn enemy_block_is_used_before_health() {
+        let (mut battle, mut rng) = start_battle(&["strike"; 10], &[enemy(MoveAction::Defend(4))]);
+        battle.end_turn();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.enemies[0].fighter.block, 4);
+
+        battle.play_card(&cards(), "strike", Target::Enemy(0)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.enemies[0].fighter.block, 0);
+        assert_eq!(battle.enemies[0].fighter.health, 28);
+        assert_eq!(battle.stats.damage_dealt, 2);
+    }
+
+    #[test]
+    fn enemies_killed_mid_turn_end_the_battle() {
+        let mut dummy = enemy(MoveAction::Attack(5));
+        dummy.health = 6;
+        let (mut battle, mut rng) = start_battle(&["strike"; 5], &[dummy]);
+        battle.play_card(&cards(), "strike", Target::Enemy(0)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.outcome(), Some(Outcome::Won));
+        assert_eq!(battle.phase, Phase::BattleEnd);
+        assert_eq!(battle.play_card(&cards(), "strike", Target::Enemy(0)), Err(PlayError::BattleOver));
+    }
+
+    #[test]
+    fn a_card_triggers_off_its_own_effects() {
+        let cards: CardDB = vec![keen_eye()].into_iter().collect();
+        let mut hidden = enemy(MoveAction::Attack(5));
+        hidden.open = false;
+        let (mut battle, mut rng) = start_battle(&["keen_eye"; 10], &[hidden]);
+
+        battle.play_card(&cards, "keen_eye", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert!(battle.enemies[0].open);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN);
+    }
+
+    #[test]
+    fn a_card_does_not_trigger_off_being_played() {
+        let (id, mut draw_on_play) = card("echo", 0, Vec::new());
+        draw_on_play.triggers = vec![TriggeredEffect {
+            trigger: Trigger::OnCardPlayed,
+            effects: vec![Effect::Draw(1)],
+        }];
+        let cards: CardDB = vec![(id, draw_on_play)].into_iter().collect();
+        let (mut battle, mut rng) = start_battle(&["echo"; 10], &[enemy(MoveAction::Attack(5))]);
+
+        battle.play_card(&cards, "echo", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN - 1);
+
+        battle.play_card(&cards, "echo", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN - 1);
+    }
+
+    #[test]
+    fn replaying_a_card_stacks_its_triggers() {
+        let cards: CardDB = vec![keen_eye()].into_iter().collect();
+        let mut hidden = enemy(MoveAction::Attack(5));
+        hidden.open = false;
+        let (mut battle, mut rng) = start_battle(&["keen_eye"; 10], &[hidden.clone(), hidden]);
+
+        battle.play_card(&cards, "keen_eye", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN);
+
+        battle.play_card(&cards, "keen_eye", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.listeners.len(), 2);
+        assert!(battle.enemies.iter().all(|enemy| enemy.open));
+        assert_eq!(battle.deck.hand.len(), CARDS_PER_TURN + 1);
+    }
+
+    fn hidden(action: MoveAction) -> Enemy {
+        let mut enemy = enemy(action);
+        enemy.open = false;
+        enemy
+    }
+
+    #[test]
+    fn healing_stops_at_max_health_without_overflowing() {
+        let mut fighter = Fighter::new(i8::MAX);
+        fighter.health = 120;
+        fighter.heal(20);
+        assert_eq!(fighter.health, i8::MAX);
+    }
+
+    #[test]
+    fn peeking_shows_the_intent_for_one_turn_without_revealing() {
+        let cards: CardDB = vec![card("peek", 0, vec![Effect::Peek])].into_iter().collect();
+        let (mut battle, mut rng) = start_battle(&["peek"; 10], &[hidden(MoveAction::Defend(0))]);
+        assert!(!battle.enemies[0].intent_known());
+
+        battle.play_card(&cards, "peek", Target::Enemy(0)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert!(battle.enemies[0].intent_known());
+        assert!(!battle.enemies[0].open);
+
+        battle.end_turn();
+        battle.resolve_all(&mut rng);
+        assert!(!battle.enemies[0].intent_known());
+    }
+
+    #[test]
+    fn revealing_triggers_only_for_hidden_enemies() {
+        let (id, mut reveal) = card("reveal", 0, vec![Effect::Reveal]);
+        reveal.triggers = vec![TriggeredEffect {
+            trigger: Trigger::OnEnemyRevealed,
+            effects: vec![Effect::GainBlock(1)],
+        }];
+        let cards: CardDB = vec![(id, reveal)].into_iter().collect();
+        let enemies = [hidden(MoveAction::Defend(0)), enemy(MoveAction::Defend(0))];
+        let (mut battle, mut rng) = start_battle(&["reveal"; 10], &enemies);
+
+        battle.play_card(&cards, "reveal", Target::Enemy(0)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert!(battle.enemies[0].open);
+        assert_eq!(battle.player.block, 1);
+
+        // already open, so nothing fires
+        battle.play_card(&cards, "reveal", Target::Enemy(1)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.player.block, 1);
+    }
+
+    #[test]
+    fn damage_per_revealed_counts_the_enemy_being_hit() {
+        let cards: CardDB = vec![card("expose", 1, vec![Effect::DealDamagePerRevealed(3)])].into_iter().collect();
+        let enemies = [enemy(MoveAction::Defend(0)), hidden(MoveAction::Defend(0)), hidden(MoveAction::Defend(0))];
+        let (mut battle, mut rng) = start_battle(&["expose"; 10], &enemies);
+
+        battle.play_card(&cards, "expose", Target::Enemy(1)).unwrap();
+        battle.resolve_all(&mut rng);
+        assert!(battle.enemies[1].open);
+        assert!(!battle.enemies[2].open);
+        assert_eq!(battle.enemies[1].fighter.health, 30 - 6);
+    }
+
+    #[test]
+    fn block_per_revealed_counts_open_enemies() {
+        let cards: CardDB = vec![card("watch", 1, vec![Effect::GainBlockPerRevealed(4)])].into_iter().collect();
+        let enemies = [enemy(MoveAction::Defend(0)), enemy(MoveAction::Defend(0)), hidden(MoveAction::Defend(0))];
+        let (mut battle, mut rng) = start_battle(&["watch"; 10], &enemies);
+
+        battle.play_card(&cards, "watch", Target::None).unwrap();
+        battle.resolve_all(&mut rng);
+        assert_eq!(battle.player.block, 8);
+        assert!(!battle.enemies[2].open);
+    }
+
+    fn war_drum() -> Relic {
+        Relic {
+            id: "war_drum".to_string(),
+            name: "War drum".to_string(),
+            color: (0, 0, 0),
+            trigger: Trigger::OnCardPlayed,
+            every: 3,
+            effects: vec![Effect::DoubleDamage],
+        }
+    }
+
+    // Free cards so a whole hand can be played in one turn
+    fn drum_cards() -> CardDB {
+        let (id, mut volley) = card("volley", 0, vec![Effect::DealDamage(2)]);
+        volley.cost = Cost::X;
+        vec![
+            card("strike", 0, vec![Effect::DealDamage(6)]),
+            card("defend", 0, vec![Effect::GainBlock(5)]),
+            card("twin", 0, vec![Effect::DealDamage(2), Effect::DealDamage(3)]),
+            (id, volley),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    // Plays each card at the first enemy, resolving in between, and returns
+    // the damage each play dealt
+    fn play_all(battle: &mut Battle, rng: &mut RunRng, hand: &[&str]) -> Vec<i8> {
+        let cards = drum_cards();
+        battle.deck.hand = hand.iter().map(|id| id.to_string()).collect();
+        hand.iter()
+            .map(|id| {
+                let before = battle.enemies[0].fighter.health;
+                let target = if needs_target(&cards[*id].effects) { Target::Enemy(0) } else { Target::None };
+                battle.play_card(&cards, id, target).unwrap();
+                battle.resolve_all(rng);
+                before - battle.enemies[0].fighter.health
+            })
+            .collect()
+    }
+
+    // A battle against an enemy that won't die or fight back, with the war
+    // drum `counter` cards in
+    fn drum_battle(counter: u32) -> (Battle, RunRng) {
+        let mut dummy = enemy(MoveAction::Defend(0));
+        dummy.health = 100;
+        let (mut battle, rng) = start_battle(&["strike"; 10], &[dummy]);
+        battle.add_relic(&war_drum(), counter);
+        (battle, rng)
+    }
+
+    #[test]
+    fn the_war_drum_doubles_only_the_third_card() {
+        let (mut battle, mut rng) = drum_battle(0);
+        assert_eq!(play_all(&mut battle, &mut rng, &["strike", "strike", "strike", "strike"]), vec![6, 6, 12, 6]);
+        assert_eq!(battle.damage_multiplier, 1);
+    }
+
+    #[test]
+    fn the_war_drum_doubles_every_hit_of_the_card() {
+        let (mut battle, mut rng) = drum_battle(2);
+        assert_eq!(play_all(&mut battle, &mut rng, &["twin"]), vec![10]);
+
+        // three hits of 2 for the 3 energy
+        let (mut battle, mut rng) = drum_battle(2);
+        assert_eq!(play_all(&mut battle, &mut rng, &["volley"]), vec![12]);
+    }
+
+    #[test]
+    fn the_war_drum_bonus_is_lost_on_cards_that_do_not_attack() {
+        let (mut battle, mut rng) = drum_battle(0);
+        assert_eq!(play_all(&mut battle, &mut rng, &["defend", "defend", "defend", "strike"]), vec![0, 0, 0, 6]);
+        assert_eq!(battle.player.block, 15);
+    }
+}
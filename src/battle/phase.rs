@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+// Where the battle is in its turn cycle. Phases change through queued
+// `Action::EnterPhase`s, each one runs its hook as it is entered
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    BattleStart,
+    PlayerTurnStart,
+    // the only phase the player can act in
+    PlayerMain,
+    PlayerTurnEnd,
+    EnemyTurn,
+    BattleEnd,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::BattleStart => "Battle start",
+            Phase::PlayerTurnStart => "Your turn",
+            Phase::PlayerMain => "Your turn",
+            Phase::PlayerTurnEnd => "End of turn",
+            Phase::EnemyTurn => "Enemy turn",
+            Phase::BattleEnd => "Battle over",
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Status {
+    Poison,
+    Weak,
+    Vulnerable,
+    Strength,
+    // keeps block from being cleared at turn start
+    Barricade,
+    // like barricade for a number of turns
+    Blur,
+}
+
+impl Status {
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Status::Poison => "P",
+            Status::Weak => "W",
+            Status::Vulnerable => "V",
+            Status::Strength => "S",
+            Status::Barricade => "B",
+            Status::Blur => "Bl",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Poison => "Poison",
+            Status::Weak => "Weak",
+            Status::Vulnerable => "Vulnerable",
+            Status::Strength => "Strength",
+            Status::Barricade => "Barricade",
+            Status::Blur => "Blur",
+        }
+    }
+
+    // Strength is the only status that is allowed to go negative
+    fn can_be_negative(self) -> bool {
+        self == Status::Strength
+    }
+
+    fn decays_at_turn_end(self) -> bool {
+        match self {
+            Status::Weak | Status::Vulnerable => true,
+            Status::Poison | Status::Strength | Status::Barricade | Status::Blur => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statuses(BTreeMap<Status, i8>);
+
+impl Statuses {
+    pub fn get(&self, status: Status) -> i8 {
+        self.0.get(&status).copied().unwrap_or(0)
+    }
+
+    pub fn has(&self, status: Status) -> bool {
+        self.get(status) != 0
+    }
+
+    // Stacks add together, a status is dropped once it reaches 0
+    pub fn apply(&mut self, status: Status, stacks: i8) {
+        let mut total = self.get(status).saturating_add(stacks);
+        if !status.can_be_negative() {
+            total = std::cmp::max(0, total);
+        }
+        if total == 0 {
+            self.0.remove(&status);
+        } else {
+            self.0.insert(status, total);
+        }
+    }
+
+    // Returns the poison damage the owner takes as their turn begins
+    pub fn tick_turn_start(&mut self) -> i8 {
+        let poison = self.get(Status::Poison);
+        if poison > 0 {
+            self.apply(Status::Poison, -1);
+        }
+        poison
+    }
+
+    // Whether block survives into this turn, blur is used up as it does so
+    pub fn retain_block(&mut self) -> bool {
+        if self.has(Status::Barricade) {
+            true
+        } else if self.has(Status::Blur) {
+            self.apply(Status::Blur, -1);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn tick_turn_end(&mut self) {
+        let decaying: Vec<Status> = self
+            .0
+            .keys()
+            .copied()
+            .filter(|status| status.decays_at_turn_end())
+            .collect();
+        for status in decaying {
+            self.apply(status, -1);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Status, i8)> + '_ {
+        self.0.iter().map(|(status, stacks)| (*status, *stacks))
+    }
+}
+
+// Strength adds flat damage, weak cuts the attacker's damage by a quarter and
+// vulnerable makes the defender take half again as much
+pub fn modify_damage(base: i8, attacker: &Statuses, defender: &Statuses) -> i8 {
+    let mut damage = (base as i32 + attacker.get(Status::Strength) as i32) as f32;
+    if attacker.has(Status::Weak) {
+        damage *= 0.75;
+    }
+    if defender.has(Status::Vulnerable) {
+        damage *= 1.5;
+    }
+    (damage.floor() as i32).clamp(0, i8::MAX as i32) as i8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(stacks: &[(Status, i8)]) -> Statuses {
+        let mut statuses = Statuses::default();
+        for (status, amount) in stacks {
+            statuses.apply(*status, *amount);
+        }
+        statuses
+    }
+
+    #[test]
+    fn stacks_add_together() {
+        let mut statuses = statuses(&[(Status::Poison, 3)]);
+        statuses.apply(Status::Poison, 2);
+        assert_eq!(statuses.get(Status::Poison), 5);
+        statuses.apply(Status::Poison, i8::MAX);
+        assert_eq!(statuses.get(Status::Poison), i8::MAX);
+    }
+
+    #[test]
+    fn statuses_are_removed_at_zero() {
+        let mut statuses = statuses(&[(Status::Weak, 2), (Status::Strength, 1)]);
+        statuses.apply(Status::Weak, -5);
+        assert!(!statuses.has(Status::Weak));
+        statuses.apply(Status::Strength, -1);
+        assert_eq!(statuses.iter().count(), 0);
+    }
+
+    #[test]
+    fn only_strength_goes_negative() {
+        let statuses = statuses(&[(Status::Strength, -2), (Status::Vulnerable, -2)]);
+        assert_eq!(statuses.get(Status::Strength), -2);
+        assert!(!statuses.has(Status::Vulnerable));
+    }
+
+    #[test]
+    fn weak_and_vulnerable_wear_off_at_turn_end() {
+        let mut statuses = statuses(&[
+            (Status::Weak, 2),
+            (Status::Vulnerable, 1),
+            (Status::Poison, 3),
+            (Status::Strength, 2),
+        ]);
+        statuses.tick_turn_end();
+        assert_eq!(statuses.get(Status::Weak), 1);
+        assert!(!statuses.has(Status::Vulnerable));
+        assert_eq!(statuses.get(Status::Poison), 3);
+        assert_eq!(statuses.get(Status::Strength), 2);
+        statuses.tick_turn_end();
+        assert!(!statuses.has(Status::Weak));
+    }
+
+    #[test]
+    fn poison_ticks_at_turn_start() {
+        let mut statuses = statuses(&[(Status::Poison, 2)]);
+        assert_eq!(statuses.tick_turn_start(), 2);
+        assert_eq!(statuses.tick_turn_start(), 1);
+        assert_eq!(statuses.tick_turn_start(), 0);
+        assert!(!statuses.has(Status::Poison));
+    }
+
+    #[test]
+    fn barricade_keeps_block_for_good() {
+        let mut statuses = statuses(&[(Status::Barricade, 1)]);
+        assert!(statuses.retain_block());
+        assert!(statuses.retain_block());
+        assert!(!Statuses::default().retain_block());
+    }
+
+    #[test]
+    fn blur_keeps_block_once_per_stack() {
+        let mut statuses = statuses(&[(Status::Blur, 2)]);
+        assert!(statuses.retain_block());
+        assert!(statuses.retain_block());
+        assert!(!statuses.retain_block());
+    }
+
+    #[test]
+    fn barricade_does_not_use_up_blur() {
+        let mut statuses = statuses(&[(Status::Barricade, 1), (Status::Blur, 1)]);
+        assert!(statuses.retain_block());
+        assert_eq!(statuses.get(Status::Blur), 1);
+    }
+
+    #[test]
+    fn strength_is_added_before_multipliers() {
+        let none = Statuses::default();
+        let attacker = statuses(&[(Status::Strength, 2), (Status::Weak, 1)]);
+        // (6 + 2) * 0.75
+        assert_eq!(modify_damage(6, &attacker, &none), 6);
+        let vulnerable = statuses(&[(Status::Vulnerable, 1)]);
+        // (6 + 2) * 0.75 * 1.5
+        assert_eq!(modify_damage(6, &attacker, &vulnerable), 9);
+    }
+
+    #[test]
+    fn damage_is_rounded_down() {
+        let none = Statuses::default();
+        let weak = statuses(&[(Status::Weak, 1)]);
+        let vulnerable = statuses(&[(Status::Vulnerable, 1)]);
+        // 5 * 0.75 = 3.75
+        assert_eq!(modify_damage(5, &weak, &none), 3);
+        // 5 * 1.5 = 7.5
+        assert_eq!(modify_damage(5, &none, &vulnerable), 7);
+        // 5 * 0.75 * 1.5 = 5.625
+        assert_eq!(modify_damage(5, &weak, &vulnerable), 5);
+    }
+
+    #[test]
+    fn damage_stays_in_range() {
+        let none = Statuses::default();
+        let weakened = statuses(&[(Status::Strength, -10)]);
+        assert_eq!(modify_damage(6, &weakened, &none), 0);
+        let strong = statuses(&[(Status::Strength, 100)]);
+        assert_eq!(modify_damage(100, &strong, &none), i8::MAX);
+    }
+}
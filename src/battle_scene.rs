@@ -1,88 +1,209 @@
-// use crate::components::{
-//     Active, Button, HealthBar, Mousehandler, Player, Position, Rectangle, Sprite,
-// };
-// use crate::systems::{
-//     BattleState, ButtonHandler, CardSelector, DamageQueue, DrawSys, EnemyAttacker,
-// };
-// use crate::textures::Textures;
-// use raylib::prelude::*;
-// use specs::prelude::*;
-
-// use crate::{HEIGHT, WIDTH};
-
-// struct BattleScene<'a, 'b> {
-//     world: World,
-//     dispatcher: Dispatcher<'a, 'b>,
-// }
-
-// impl<'a, 'b> BattleScene<'a, 'b> {
-//     pub fn new(rl: RaylibHandle, thread: RaylibThread) -> Self {
-//         let mut world = World::new();
-//         world.register::<Position>();
-//         world.register::<Rectangle>();
-//         world.register::<Sprite>();
-//         world.register::<Mousehandler>();
-//         world.register::<HealthBar>();
-//         world.register::<Player>();
-//         world.register::<Button>();
-//         world.register::<Active>();
-//         world.insert(rl);
-//         let mut textures = Textures::from_paths(vec!["card-back", "mouse-grab"], &mut rl, &thread);
-//         let mut dispatcher = specs::DispatcherBuilder::new()
-//             .with_thread_local(DrawSys { thread, textures })
-//             .with(
-//                 CardSelector {
-//                     selected: None,
-//                     dragging: None,
-//                 },
-//                 "card_selector",
-//                 &[],
-//             )
-//             .with(DamageQueue, "damage_queue", &["card_selector"])
-//             .with(EnemyAttacker, "enemy_attacker", &[])
-//             .with(ButtonHandler, "button_handler", &[])
-//             .build();
-//         dispatcher.setup(&mut world);
-//         BattleScene { world, dispatcher }
-//     }
-//     pub fn setup(&mut self) {
-//         self.world
-//             .create_entity()
-//             .with(Mousehandler)
-//             .with(Position { x: 0.0, y: 0.0 })
-//             .with(Rectangle {
-//                 width: 25.,
-//                 height: 25.,
-//             })
-//             .with(Active(false))
-//             .with(Sprite {
-//                 scale: 1.,
-//                 texture_path: "mouse-grab".to_string(),
-//             })
-//             .build();
-
-//         self.world
-//             .create_entity()
-//             .with(HealthBar::new(20))
-//             .with(Position { x: 50., y: 100. })
-//             .with(Rectangle {
-//                 width: 200.,
-//                 height: 50.,
-//             })
-//             .with(Player::default())
-//             .build();
-
-//         self.world
-//             .create_entity()
-//             .with(Position {
-//                 x: (WIDTH - 200) as f32,
-//                 y: (HEIGHT - 100) as f32,
-//             })
-//             .with(Rectangle {
-//                 width: 150.,
-//                 height: 50.,
-//             })
-//             .with(Button::new("End Turn", "end_turn"))
-//             .build();
-//     }
-// }
+use crate::components::{
+    Active, Button, EnemySlot, HealthBar, Mousehandler, Player, Position, Rectangle, SceneOwned,
+    Sprite, UiAction,
+};
+use crate::battle::Battle;
+use crate::enemies::Row;
+use crate::rng::RunRng;
+use crate::run::Run;
+use crate::scene::{delete_scene_entities, Scene};
+use crate::data_loading::{enemy_silhouette_name, enemy_texture_name};
+use crate::{EncountersDB, EnemiesDB, GameScenes, RelicDB, C_HEIGHT, C_WIDTH, HEIGHT, WIDTH};
+use specs::prelude::*;
+
+pub struct BattleScene {
+    pub dispatcher: Dispatcher<'static, 'static>,
+}
+
+impl Scene for BattleScene {
+    fn id(&self) -> GameScenes {
+        GameScenes::Battle
+    }
+
+    fn dispatcher(&mut self) -> &mut Dispatcher<'static, 'static> {
+        &mut self.dispatcher
+    }
+
+    // A continued run already put its saved battle in the world
+    fn setup(&mut self, world: &mut World) {
+        if !world.has_value::<Battle>() {
+            let battle = {
+                let run = world.read_resource::<Run>();
+                let enemies = world.read_resource::<EnemiesDB>();
+                let encounters = world.read_resource::<EncountersDB>();
+                let relics = world.read_resource::<RelicDB>();
+                let mut rng = world.write_resource::<RunRng>();
+                run.start_battle(&enemies, &encounters, &relics, &mut rng)
+            };
+            world.insert(battle);
+        }
+        spawn_battle_entities(world);
+    }
+
+    fn teardown(&mut self, world: &mut World) {
+        delete_scene_entities(world, GameScenes::Battle);
+        world.remove::<Battle>();
+    }
+}
+
+const FRONT_ROW_Y: f32 = 330.;
+const BACK_ROW_Y: f32 = 180.;
+// the middle of the space right of the player
+const FORMATION_CENTRE_X: f32 = 625.;
+const FORMATION_SPACING: f32 = C_WIDTH as f32 + 60.;
+
+// Lays each row out centred on the same point. Rows with counts of the same
+// parity would line up, so then the back row is staggered by half a slot to
+// show between the enemies in front
+fn formation_positions(rows: &[Row]) -> Vec<Position> {
+    let count = |row| rows.iter().filter(|r| **r == row).count();
+    let (front_count, back_count) = (count(Row::Front), count(Row::Back));
+    let back_stagger = if front_count % 2 == back_count % 2 {
+        FORMATION_SPACING / 2.
+    } else {
+        0.
+    };
+    let (mut front_index, mut back_index) = (0, 0);
+    rows.iter()
+        .map(|row| {
+            let (index, count, y, stagger) = match row {
+                Row::Front => (&mut front_index, front_count, FRONT_ROW_Y, 0.),
+                Row::Back => (&mut back_index, back_count, BACK_ROW_Y, back_stagger),
+            };
+            let offset = *index as f32 - (count as f32 - 1.) / 2.;
+            *index += 1;
+            Position::new(
+                FORMATION_CENTRE_X + offset * FORMATION_SPACING + stagger - C_WIDTH as f32 / 2.,
+                y,
+            )
+        })
+        .collect()
+}
+
+// Builds the entities for whatever `Battle` is in the world
+fn spawn_battle_entities(world: &mut World) {
+    let (player, enemies) = {
+        let battle = world.read_resource::<Battle>();
+        (battle.player.clone(), battle.enemies.clone())
+    };
+    let rows: Vec<Row> = enemies.iter().map(|enemy| enemy.row).collect();
+
+    world
+        .create_entity()
+        .with(Mousehandler)
+        .with(Position { x: 0.0, y: 0.0 })
+        .with(Rectangle {
+            width: 25.,
+            height: 25.,
+        })
+        .with(Active(false))
+        .with(Sprite {
+            scale: 1.,
+            texture_path: "mouse-grab".to_string(),
+        })
+        .with(SceneOwned(GameScenes::Battle))
+        .build();
+
+    world
+        .create_entity()
+        .with(HealthBar::new(&player))
+        .with(Position { x: 50., y: 100. })
+        .with(Rectangle {
+            width: 200.,
+            height: 50.,
+        })
+        .with(Player::default())
+        .with(SceneOwned(GameScenes::Battle))
+        .build();
+
+    world
+        .create_entity()
+        .with(Position {
+            x: (WIDTH - 200) as f32,
+            y: (HEIGHT - 100) as f32,
+        })
+        .with(Rectangle {
+            width: 150.,
+            height: 50.,
+        })
+        .with(Button::new("End Turn", UiAction::EndTurn))
+        .with(SceneOwned(GameScenes::Battle))
+        .build();
+
+    for (index, (enemy, position)) in enemies.iter().zip(formation_positions(&rows)).enumerate() {
+        // a continued battle can have enemies that already died
+        if enemy.fighter.is_dead() {
+            continue;
+        }
+        let texture_path = if enemy.open {
+            enemy_texture_name(&enemy.id)
+        } else {
+            enemy_silhouette_name(&enemy.id)
+        };
+        let builder = world
+            .create_entity()
+            .with(EnemySlot(index))
+            .with(position)
+            .with(Rectangle {
+                width: C_WIDTH as f32,
+                height: C_HEIGHT as f32,
+            })
+            .with(Sprite { texture_path, scale: 1. })
+            .with(Active(true))
+            .with(SceneOwned(GameScenes::Battle));
+        // hidden enemies get theirs from `BattleSync` once revealed
+        let builder = if enemy.open {
+            builder.with(HealthBar::new(&enemy.fighter))
+        } else {
+            builder
+        };
+        builder.build();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // x of the middle of each slot
+    fn slot_centres(positions: &[Position]) -> Vec<f32> {
+        positions.iter().map(|position| position.x + C_WIDTH as f32 / 2.).collect()
+    }
+
+    #[test]
+    fn rows_are_placed_front_and_back_in_order() {
+        let positions = formation_positions(&[Row::Back, Row::Front, Row::Front]);
+        let ys: Vec<f32> = positions.iter().map(|position| position.y).collect();
+        assert_eq!(ys, vec![BACK_ROW_Y, FRONT_ROW_Y, FRONT_ROW_Y]);
+    }
+
+    #[test]
+    fn each_row_is_centred_and_evenly_spaced() {
+        let positions = formation_positions(&[Row::Front, Row::Front, Row::Front, Row::Back, Row::Back]);
+        let centres = slot_centres(&positions);
+        let front = [
+            FORMATION_CENTRE_X - FORMATION_SPACING,
+            FORMATION_CENTRE_X,
+            FORMATION_CENTRE_X + FORMATION_SPACING,
+        ];
+        assert_eq!(centres[..3], front);
+        // an odd front row and an even back row already fall between each other
+        let back = [FORMATION_CENTRE_X - FORMATION_SPACING / 2., FORMATION_CENTRE_X + FORMATION_SPACING / 2.];
+        assert_eq!(centres[3..], back);
+    }
+
+    #[test]
+    fn back_rows_with_the_same_parity_are_staggered() {
+        let centres = slot_centres(&formation_positions(&[Row::Front, Row::Back]));
+        assert_eq!(centres, vec![FORMATION_CENTRE_X, FORMATION_CENTRE_X + FORMATION_SPACING / 2.]);
+
+        let centres = slot_centres(&formation_positions(&[Row::Front, Row::Front, Row::Back, Row::Back]));
+        assert_eq!(centres[2..], [FORMATION_CENTRE_X, FORMATION_CENTRE_X + FORMATION_SPACING]);
+    }
+
+    #[test]
+    fn a_back_row_alone_is_still_centred() {
+        let centres = slot_centres(&formation_positions(&[Row::Back]));
+        assert_eq!(centres, vec![FORMATION_CENTRE_X]);
+    }
+}
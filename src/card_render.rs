@@ -0,0 +1,105 @@
+// Draws a card's face onto `card.png`, the result is cached by
+// `generate_card_textures` so this only runs once per card at startup
+use crate::cards::{Card, Rarity};
+use crate::textures::load_image;
+
+use raylib::core::text::WeakFont;
+use raylib::prelude::*;
+
+const SCALE: i32 = 2;
+const TEXT_SPACING: f32 = 1.;
+const PADDING: f32 = 6.;
+const COST_SIZE: i32 = 20;
+const MIN_FONT_SIZE: f32 = 8.;
+const NAME_FONT_SIZE: f32 = 12.;
+const DESCRIPTION_FONT_SIZE: f32 = 11.;
+
+fn rarity_color(rarity: Rarity) -> Color {
+    match rarity {
+        Rarity::Common => Color::LIGHTGRAY,
+        Rarity::Uncommon => Color::SKYBLUE,
+        Rarity::Rare => Color::GOLD,
+    }
+}
+
+fn text_width(font: &WeakFont, text: &str, size: f32) -> f32 {
+    measure_text_ex(font, text, size, TEXT_SPACING).x
+}
+
+fn line_height(size: f32) -> f32 {
+    size + 2.
+}
+
+// Greedy word wrap, newlines in `text` always start a new line. A single word
+// wider than `max_width` gets a line to itself rather than being split
+pub fn wrap_text(font: &WeakFont, text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && text_width(font, &candidate, size) > max_width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// The largest font size from `max_size` down that fits `bounds` once wrapped,
+// text that doesn't fit even at the minimum is drawn at the minimum anyway
+fn fit_text(font: &WeakFont, text: &str, bounds: Rectangle, max_size: f32) -> (f32, Vec<String>) {
+    let mut size = max_size;
+    loop {
+        let lines = wrap_text(font, text, size, bounds.width);
+        let fits_width = lines.iter().all(|line| text_width(font, line, size) <= bounds.width);
+        let fits_height = lines.len() as f32 * line_height(size) <= bounds.height;
+        if (fits_width && fits_height) || size <= MIN_FONT_SIZE {
+            return (size, lines);
+        }
+        size -= 1.;
+    }
+}
+
+fn draw_fitted(image: &mut Image, font: &WeakFont, text: &str, bounds: Rectangle, max_size: f32, color: Color) {
+    let (size, lines) = fit_text(font, text, bounds, max_size);
+    let height = lines.len() as f32 * line_height(size);
+    let top = bounds.y + ((bounds.height - height) / 2.).max(0.);
+    for (index, line) in lines.iter().enumerate() {
+        let x = bounds.x + ((bounds.width - text_width(font, line, size)) / 2.).max(0.);
+        let y = top + index as f32 * line_height(size);
+        image.image_draw_text_ex(Vector2 { x, y }, font, line, size, TEXT_SPACING, color);
+    }
+}
+
+pub fn render_card(font: &WeakFont, card: &Card) -> Image {
+    let mut image = load_image("assets/card.png");
+    image.image_resize_nn(image.width * SCALE, image.height * SCALE);
+    let width = image.width as f32;
+    let height = image.height as f32;
+
+    image.image_draw_rectangle_lines(Rectangle::new(0., 0., width, height), 3, rarity_color(card.rarity));
+
+    image.image_draw_rectangle(PADDING as i32, PADDING as i32, COST_SIZE, COST_SIZE, Color::DARKBLUE);
+    let cost = card.cost.to_string();
+    let cost_x = PADDING + (COST_SIZE as f32 - text_width(font, &cost, NAME_FONT_SIZE)) / 2.;
+    let cost_y = PADDING + (COST_SIZE as f32 - NAME_FONT_SIZE) / 2.;
+    image.image_draw_text_ex(Vector2 { x: cost_x, y: cost_y }, font, &cost, NAME_FONT_SIZE, TEXT_SPACING, Color::GOLD);
+
+    let name_x = PADDING * 2. + COST_SIZE as f32;
+    let name_bounds = Rectangle::new(name_x, PADDING, width - name_x - PADDING, COST_SIZE as f32 + 4.);
+    draw_fitted(&mut image, font, &card.name, name_bounds, NAME_FONT_SIZE, Color::WHITE);
+
+    // bottom half of the card, the top is left for art
+    let description_bounds = Rectangle::new(PADDING, height / 2., width - PADDING * 2., height / 2. - PADDING);
+    draw_fitted(&mut image, font, &card.description(), description_bounds, DESCRIPTION_FONT_SIZE, Color::WHITE);
+
+    image
+}
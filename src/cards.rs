@@ -1,18 +1,78 @@
-
+use crate::effects::{describe_effects, Effect, TriggeredEffect};
 use crate::{CardID, CardDB};
-use crate::components::Card;
-use rand::{thread_rng, Rng};
+use crate::rng::{RunRng, Stream};
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct Card {
+    pub id: CardID,
+    pub name: String,
+    pub cost: Cost,
+    #[serde(default)]
+    pub rarity: Rarity,
+    pub effects: Vec<Effect>,
+    #[serde(default)]
+    pub triggers: Vec<TriggeredEffect>,
+}
+
+impl Card {
+    pub fn description(&self) -> String {
+        let mut text = match self.cost {
+            Cost::X => format!("X times:\n{}", describe_effects(&self.effects)),
+            Cost::Fixed(_) => describe_effects(&self.effects),
+        };
+        for trigger in &self.triggers {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&trigger.describe());
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+}
+
+// X cards spend all remaining energy and resolve their effects once per point
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum Cost {
+    Fixed(u8),
+    X,
+}
+
+impl Default for Cost {
+    fn default() -> Self {
+        Cost::Fixed(1)
+    }
+}
+
+impl fmt::Display for Cost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cost::Fixed(cost) => write!(f, "{}", cost),
+            Cost::X => write!(f, "X"),
+        }
+    }
+}
 
 
-pub fn generate_intial_deck(cards_db: &CardDB) -> Vec<CardID> {
+pub fn generate_intial_deck(cards_db: &CardDB, rng: &mut RunRng) -> Vec<CardID> {
     let mut deck = Vec::new();
 
-    let mut rng = rand::thread_rng();
+    let rng = rng.stream(Stream::StartingDeck);
 
     for _ in 0..10 {
         let index = rng.gen_range(0, cards_db.len());
         let card = cards_db.iter().nth(index).unwrap();
-        deck.push(*card.0);
+        deck.push(card.0.clone());
     }
     deck
 }
\ No newline at end of file
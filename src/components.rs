@@ -1,7 +1,11 @@
 use specs::{Component, VecStorage, NullStorage};
 use raylib::math::Vector2;
+use raylib::prelude::Color;
 use std::ops::{Mul, Add, AddAssign, Sub, SubAssign, MulAssign, Div, DivAssign, Neg};
-use serde::{Serialize, Deserialize};
+
+use crate::battle::status::Statuses;
+use crate::battle::Fighter;
+use crate::{CardID, GameScenes};
 
 #[derive(Component, Debug, PartialEq, Default, Copy, Clone)]
 #[storage(VecStorage)]
@@ -190,39 +194,62 @@ pub struct Sprite {
     pub scale: f32,
 }
 
-#[derive(Component, Debug, Default, Deserialize, Clone)]
+// The scene that spawned this entity, it is deleted when that scene leaves
+// the stack
+#[derive(Component, Debug, Clone, Copy)]
 #[storage(VecStorage)]
-pub struct Card {
-    pub value: i8,
-    pub name: String,
-    pub effect_description: String,
-}
+pub struct SceneOwned(pub GameScenes);
+
+// Index into `Battle::enemies` for the entity drawing that enemy
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct EnemySlot(pub usize);
 
-#[derive(Component, Debug, Default, Deserialize, Clone)]
+// A card in the player's hand, `index` is its place in `Deck::hand`
+#[derive(Component, Debug, Clone)]
 #[storage(VecStorage)]
-pub struct Enemy {
-    pub name: String,
-    pub health: i8,
-    pub attack: i8,
-    pub open: bool
+pub struct HandCard {
+    pub card_id: CardID,
+    pub index: usize,
+    pub hovered: bool,
 }
 
+// Degrees, sprites turn around their bottom centre
+#[derive(Component, Debug, Default, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct Rotation(pub f32);
+
+// Copied from the battle each frame so statuses can be drawn next to health bars
+#[derive(Component, Debug, Default, Clone)]
+#[storage(VecStorage)]
+pub struct StatusBar(pub Statuses);
+
 #[derive(Component, Debug, Default, Clone, Copy)]
 #[storage(VecStorage)]
 pub struct HealthBar {
     pub max: i8,
-    pub current: i8
+    pub current: i8,
+    pub block: i8,
+    // what the bar draws, eased towards `current` by a tween
+    pub shown: f32,
 }
 
 impl HealthBar {
-    pub fn new(health: i8) -> Self {
+    pub fn new(fighter: &Fighter) -> Self {
         HealthBar {
-            max: health,
-            current: health
+            max: fighter.max_health,
+            current: fighter.health,
+            block: fighter.block,
+            shown: fighter.health as f32,
         }
     }
 }
 
+// Multiplied into the sprite's colour, tweened to fade things out
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct Tint(pub Color);
+
 #[derive(Component, Debug, Default, Clone)]
 #[storage(NullStorage)]
 pub struct Player;
@@ -232,17 +259,29 @@ pub struct Player;
 #[storage(NullStorage)]
 pub struct Mousehandler;
 
-#[derive(Component, Debug, Default, Clone, Copy)]
+// Sent through an `EventChannel<UiAction>` when a `Button` is clicked, each
+// scene reads the ones it cares about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiAction {
+    StartRun,
+    ContinueRun,
+    HowToPlay,
+    EndTurn,
+    SkipReward,
+    MainMenu,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
 #[storage(VecStorage)]
 pub struct Button {
     pub text: &'static str,
-    pub action: &'static str,
+    pub action: UiAction,
     pub color: [u8; 3],
     pub hover: bool
 }
 
 impl Button {
-    pub fn new(text: &'static str, action: &'static str) -> Self {
+    pub fn new(text: &'static str, action: UiAction) -> Self {
         Button {
             text,
             action,
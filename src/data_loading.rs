@@ -1,104 +1,294 @@
-use crate::components::{Card, Enemy};
-use crate::textures::Textures;
+use crate::card_render::render_card;
+use crate::cards::Card;
+use crate::enemies::{Difficulty, Encounter, Enemy};
+use crate::relics::Relic;
+use crate::run::ACT;
+use crate::textures::{load_image, Textures};
 
 use raylib::prelude::*;
-use ron::de::from_reader;
-use std::fs::File;
-use std::collections::BTreeMap;
+use ron::de::{Deserializer, Error as RonError};
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
 
 
-use crate::{EnemyID, CardID, CardDB, EnemiesDB};
+use crate::{CardDB, EncountersDB, EnemiesDB, RelicDB};
 
 const C_WIDTH: i32 = 60;
 const C_HEIGHT: i32 = 80;
 
-pub fn get_enemies_from_file() -> EnemiesDB {
-    let f = File::open("assets/enemies.ron").expect("Failed opening file");
-    let loaded_enemies: Vec<Enemy> = match from_reader(f) {
-        Ok(x) => x,
-        Err(e) => {
-            println!("Failed to load enemies: {}", e);
+pub const CARDS_PATH: &str = "assets/cards.ron";
+pub const ENEMIES_PATH: &str = "assets/enemies.ron";
+pub const ENCOUNTERS_PATH: &str = "assets/encounters.ron";
+pub const RELICS_PATH: &str = "assets/relics.ron";
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        file: &'static str,
+        error: std::io::Error,
+    },
+    Parse {
+        file: &'static str,
+        line: usize,
+        column: usize,
+        // the last key before the error, serde's messages don't always say
+        field: Option<String>,
+        message: String,
+    },
+    Invalid {
+        file: &'static str,
+        entry: String,
+        message: String,
+    },
+}
 
-            std::process::exit(1);
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { file, error } => write!(f, "{}: {}", file, error),
+            LoadError::Parse { file, line, column, field, message } => {
+                write!(f, "{}:{}:{}: ", file, line, column)?;
+                if let Some(field) = field {
+                    write!(f, "in `{}`: ", field)?;
+                }
+                write!(f, "{}", message)
+            }
+            LoadError::Invalid { file, entry, message } => write!(f, "{}: {}: {}", file, entry, message),
         }
-    };
+    }
+}
 
-    let mut enemies: EnemiesDB = BTreeMap::new();
-    let mut id: EnemyID = 0;
-    for enemy in loaded_enemies {
-        enemies.insert(id, enemy);
-        id+= 1;
+// 1 based line and column of a byte offset
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+// Walks back from the error to the closest `key:`
+fn field_before(text: &str, offset: usize) -> Option<String> {
+    let before = &text[..offset];
+    let colon = before.rfind(':')?;
+    let key = before[..colon].trim_end();
+    let start = key
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    let key = &key[start..];
+    if key.is_empty() {
+        None
+    } else {
+        Some(key.to_string())
     }
-    
-    enemies
 }
 
-pub fn get_cards_from_file() -> CardDB {
-    let f = File::open("assets/cards.ron").expect("Failed opening file");
-    let loaded_cards: Vec<Card> = match from_reader(f) {
-        Ok(x) => x,
-        Err(e) => {
-            println!("Failed to load cards: {}", e);
+fn parse_file<T: DeserializeOwned>(file: &'static str) -> Result<T, LoadError> {
+    let text = fs::read_to_string(file).map_err(|error| LoadError::Io { file, error })?;
+    parse_text(file, &text)
+}
 
-            std::process::exit(1);
+fn parse_text<T: DeserializeOwned>(file: &'static str, text: &str) -> Result<T, LoadError> {
+    let parse_error = |offset: usize, message: String| {
+        let (line, column) = line_column(text, offset);
+        LoadError::Parse {
+            file,
+            line,
+            column,
+            // serde already names the field for these, and the error sits at
+            // the end of the struct rather than after a key
+            field: if message.starts_with("missing field") {
+                None
+            } else {
+                field_before(text, offset)
+            },
+            message,
         }
     };
+    let mut deserializer = Deserializer::from_str(text).map_err(|e| parse_error(0, e.to_string()))?;
+    let value = T::deserialize(&mut deserializer).and_then(|value| deserializer.end().map(|_| value));
+    match value {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            let offset = text.len() - deserializer.remainder().len();
+            let message = match e {
+                RonError::Parser(code, _) => format!("{:?}", code),
+                other => other.to_string(),
+            };
+            Err(parse_error(offset, message))
+        }
+    }
+}
+
+pub fn get_enemies_from_file() -> Result<Vec<Enemy>, LoadError> {
+    parse_file(ENEMIES_PATH)
+}
+
+pub fn get_cards_from_file() -> Result<Vec<Card>, LoadError> {
+    parse_file(CARDS_PATH)
+}
 
-    let mut cards: CardDB = BTreeMap::new();
-    let mut id: EnemyID = 0;
+pub fn get_encounters_from_file() -> Result<Vec<Encounter>, LoadError> {
+    parse_file(ENCOUNTERS_PATH)
+}
+
+pub fn get_relics_from_file() -> Result<Vec<Relic>, LoadError> {
+    parse_file(RELICS_PATH)
+}
 
-    for card in loaded_cards {
-        cards.insert(id, card);
-        id+= 1;
+pub fn card_texture_name(id: &str) -> String {
+    format!("id_{}_card", id)
+}
+
+pub fn enemy_texture_name(id: &str) -> String {
+    format!("id_{}_enemy", id)
+}
+
+pub fn enemy_silhouette_name(id: &str) -> String {
+    format!("id_{}_silhouette", id)
+}
+
+fn invalid(file: &'static str, entry: &str, message: &str) -> LoadError {
+    LoadError::Invalid {
+        file,
+        entry: entry.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn validate_ids<'a>(file: &'static str, ids: impl Iterator<Item = &'a String>) -> Vec<LoadError> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for id in ids {
+        if id.is_empty() {
+            errors.push(invalid(file, "entry", "id is empty"));
+        } else if !seen.insert(id) {
+            errors.push(invalid(file, id, "id is used more than once"));
+        }
     }
+    errors
+}
 
-    cards
+pub fn validate_cards(cards: &[Card]) -> Vec<LoadError> {
+    let mut errors = validate_ids(CARDS_PATH, cards.iter().map(|card| &card.id));
+    if cards.is_empty() {
+        errors.push(invalid(CARDS_PATH, "cards", "there are no cards"));
+    }
+    for card in cards {
+        if card.name.is_empty() {
+            errors.push(invalid(CARDS_PATH, &card.id, "name is empty"));
+        }
+        if card.effects.is_empty() && card.triggers.is_empty() {
+            errors.push(invalid(CARDS_PATH, &card.id, "has no effects"));
+        }
+        if card.triggers.iter().any(|trigger| trigger.effects.is_empty()) {
+            errors.push(invalid(CARDS_PATH, &card.id, "has a trigger with no effects"));
+        }
+    }
+    errors
+}
+
+pub fn validate_enemies(enemies: &[Enemy]) -> Vec<LoadError> {
+    let mut errors = validate_ids(ENEMIES_PATH, enemies.iter().map(|enemy| &enemy.id));
+    if enemies.is_empty() {
+        errors.push(invalid(ENEMIES_PATH, "enemies", "there are no enemies"));
+    }
+    for enemy in enemies {
+        if enemy.health <= 0 {
+            errors.push(invalid(ENEMIES_PATH, &enemy.id, "health must be above 0"));
+        }
+        if enemy.moves.iter().all(|m| m.weight == 0) {
+            errors.push(invalid(ENEMIES_PATH, &enemy.id, "needs at least one move with a weight above 0"));
+        }
+    }
+    errors
+}
+
+pub fn validate_encounters(encounters: &[Encounter], enemies: &[Enemy]) -> Vec<LoadError> {
+    let mut errors = validate_ids(ENCOUNTERS_PATH, encounters.iter().map(|encounter| &encounter.id));
+    for encounter in encounters {
+        if encounter.enemies.is_empty() {
+            errors.push(invalid(ENCOUNTERS_PATH, &encounter.id, "has no enemies"));
+        }
+        for slot in &encounter.enemies {
+            if !enemies.iter().any(|enemy| enemy.id == slot.enemy) {
+                let message = format!("enemy `{}` is not in {}", slot.enemy, ENEMIES_PATH);
+                errors.push(invalid(ENCOUNTERS_PATH, &encounter.id, &message));
+            }
+        }
+    }
+    // every kind of fight on the map needs something to pick from
+    for difficulty in &[Difficulty::Easy, Difficulty::Normal, Difficulty::Elite, Difficulty::Boss] {
+        if !encounters.iter().any(|encounter| encounter.act == ACT && encounter.difficulty == *difficulty) {
+            let message = format!("there are no {:?} encounters in act {}", difficulty, ACT);
+            errors.push(invalid(ENCOUNTERS_PATH, "encounters", &message));
+        }
+    }
+    errors
+}
+
+pub fn validate_relics(relics: &[Relic]) -> Vec<LoadError> {
+    let mut errors = validate_ids(RELICS_PATH, relics.iter().map(|relic| &relic.id));
+    for relic in relics {
+        if relic.name.is_empty() {
+            errors.push(invalid(RELICS_PATH, &relic.id, "name is empty"));
+        }
+        if relic.effects.is_empty() {
+            errors.push(invalid(RELICS_PATH, &relic.id, "has no effects"));
+        }
+        if relic.every == 0 {
+            errors.push(invalid(RELICS_PATH, &relic.id, "every must be above 0"));
+        }
+    }
+    errors
+}
+
+// Loads and checks every data file, collecting all the problems instead of
+// stopping at the first one
+pub fn load_assets() -> Result<(CardDB, EnemiesDB, EncountersDB, RelicDB), Vec<LoadError>> {
+    let mut errors = Vec::new();
+    let cards = get_cards_from_file().map_err(|e| errors.push(e)).ok();
+    let enemies = get_enemies_from_file().map_err(|e| errors.push(e)).ok();
+    let encounters = get_encounters_from_file().map_err(|e| errors.push(e)).ok();
+    let relics = get_relics_from_file().map_err(|e| errors.push(e)).ok();
+    if let Some(cards) = &cards {
+        errors.extend(validate_cards(cards));
+    }
+    if let Some(enemies) = &enemies {
+        errors.extend(validate_enemies(enemies));
+    }
+    if let (Some(encounters), Some(enemies)) = (&encounters, &enemies) {
+        errors.extend(validate_encounters(encounters, enemies));
+    }
+    if let Some(relics) = &relics {
+        errors.extend(validate_relics(relics));
+    }
+    match (cards, enemies, encounters, relics) {
+        (Some(cards), Some(enemies), Some(encounters), Some(relics)) if errors.is_empty() => Ok((
+            cards.into_iter().map(|card| (card.id.clone(), card)).collect::<CardDB>(),
+            enemies.into_iter().map(|enemy| (enemy.id.clone(), enemy)).collect::<EnemiesDB>(),
+            encounters
+                .into_iter()
+                .map(|encounter| (encounter.id.clone(), encounter))
+                .collect::<EncountersDB>(),
+            relics.into_iter().map(|relic| (relic.id.clone(), relic)).collect::<RelicDB>(),
+        )),
+        _ => Err(errors),
+    }
 }
 
 
 pub fn generate_card_textures(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut Textures, cards: &CardDB) {
+    let font = rl.get_font_default();
     for (id, card) in cards {
-        let template_name = format!("id_{}_card", id);
+        let template_name = card_texture_name(id);
         if !textures.contains(&template_name) {
-            let base_image_path = "assets/enemy-front.png";
-            let mut i = Image::load_image(&base_image_path).expect("could not load image");
-            i.image_resize_nn(i.width * 2, i.height * 2);
-            i.image_draw_text_ex(
-                Vector2 { x: 5., y: 2. },
-                &rl.get_font_default(),
-                &card.name,
-                10.,
-                1.,
-                Color::WHITE,
-            );
-            i.image_draw_text_ex(
-                Vector2 { x: 5., y: 15. },
-                &rl.get_font_default(),
-                &format!("attack: {}", &card.value.to_string()),
-                10.,
-                1.,
-                Color::WHITE,
-            );
-            use std::str;
-            let string = format!("effect: {}", &card.effect_description);
-            let descriptions: Vec<&str> = string.split('\n').collect();
-            for (index, d) in descriptions.iter().enumerate() {
-                i.image_draw_text_ex(
-                    Vector2 {
-                        x: 5.,
-                        y: 30. + (index * 10) as f32,
-                    },
-                    &rl.get_font_default(),
-                    &d,
-                    10.,
-                    1.,
-                    Color::WHITE,
-                );
+            let i = render_card(&font, card);
+            match rl.load_texture_from_image(&thread, &i) {
+                Ok(texture) => textures.add(&template_name, texture),
+                Err(e) => println!("Failed to create texture for {}: {}", card.name, e),
             }
-            let texture = rl
-                .load_texture_from_image(&thread, &i)
-                .expect("could not load texture from image");
-            textures.add(&card.name, texture);
         }
     }
 }
@@ -106,10 +296,10 @@ pub fn generate_card_textures(rl: &mut RaylibHandle, thread: &RaylibThread, text
 
 pub fn generate_enemy_textures(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut Textures, enemies: &EnemiesDB) {
     for (id, enemy) in enemies {
-        let template_name = format!("id_{}_enemy", id);
+        let template_name = enemy_texture_name(id);
         if !textures.contains(&template_name) {
             let base_image_path = "assets/enemy-front.png";
-            let mut i = Image::load_image(&base_image_path).expect("could not load image");
+            let mut i = load_image(base_image_path);
             i.image_draw_text_ex(
                 Vector2 { x: 5., y: 2. },
                 &rl.get_font_default(),
@@ -124,13 +314,80 @@ pub fn generate_enemy_textures(rl: &mut RaylibHandle, thread: &RaylibThread, tex
                     y: C_HEIGHT as f32 - 13.,
                 },
                 &rl.get_font_default(),
-                &format!("attack: {}", enemy.attack),
+                &format!("hp: {}", enemy.health),
                 10.,
                 1.,
                 Color::WHITE,
             );
-            let t = rl.load_texture_from_image(&thread, &i).unwrap();
-            textures.add(&template_name, t);
+            // hidden enemies only show their outline
+            let mut silhouette = load_image(base_image_path);
+            silhouette.image_color_tint(Color::BLACK);
+            match rl.load_texture_from_image(&thread, &i) {
+                Ok(t) => textures.add(&template_name, t),
+                Err(e) => println!("Failed to create texture for {}: {}", enemy.name, e),
+            }
+            match rl.load_texture_from_image(&thread, &silhouette) {
+                Ok(t) => textures.add(&enemy_silhouette_name(id), t),
+                Err(e) => println!("Failed to create silhouette for {}: {}", enemy.name, e),
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> (usize, usize, Option<String>, String) {
+        match parse_text::<Vec<Enemy>>("test.ron", text) {
+            Err(LoadError::Parse { file, line, column, field, message }) => {
+                assert_eq!(file, "test.ron");
+                (line, column, field, message)
+            }
+            other => panic!("expected a parse error, got {:?}", other),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn well_formed_files_parse() {
+        let text = "[(id: \"rat\", name: \"Rat\", health: 5, open: true, moves: [])]";
+        let enemies = parse_text::<Vec<Enemy>>("test.ron", text).unwrap();
+        assert_eq!(enemies[0].id, "rat");
+    }
+
+    #[test]
+    fn wrong_types_name_the_field() {
+        let text = "[\n    (\n        id: \"rat\",\n        name: \"Rat\",\n        health: \"lots\",\n        open: true,\n        moves: [],\n    ),\n]";
+        let (line, column, field, _) = parse_error(text);
+        assert_eq!((line, column), (5, 17));
+        assert_eq!(field.as_deref(), Some("health"));
+    }
+
+    #[test]
+    fn syntax_errors_name_the_field() {
+        let text = "[\n    (\n        id: \"rat\",\n        open: maybe,\n    ),\n]";
+        let (line, column, field, message) = parse_error(text);
+        assert_eq!((line, column), (4, 15));
+        assert_eq!(field.as_deref(), Some("open"));
+        assert_eq!(message, "ExpectedBoolean");
+    }
+
+    #[test]
+    fn missing_fields_are_left_to_serde() {
+        let text = "[\n    (\n        id: \"rat\",\n        name: \"Rat\",\n    ),\n]";
+        let (_, _, field, message) = parse_error(text);
+        assert_eq!(field, None);
+        assert!(message.contains("health"), "{}", message);
+    }
+
+    #[test]
+    fn errors_print_as_file_line_and_column() {
+        let error = LoadError::Parse {
+            file: "test.ron",
+            line: 3,
+            column: 7,
+            field: Some("health".to_string()),
+            message: "expected i8".to_string(),
+        };
+        assert_eq!(error.to_string(), "test.ron:3:7: in `health`: expected i8");
+    }
+}
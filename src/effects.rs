@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::battle::status::Status;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Effect {
+    DealDamage(i8),
+    // multiplied by the number of revealed enemies still alive
+    DealDamagePerRevealed(i8),
+    Draw(u8),
+    FlipRandomEnemy,
+    Reveal,
+    // shows a hidden enemy's intent for this turn without revealing it
+    Peek,
+    Heal(i8),
+    GainBlock(i8),
+    GainBlockPerRevealed(i8),
+    GainEnergy(u8),
+    ApplyStatus(Status, i8),
+    GainStatus(Status, i8),
+    // for the card being played, its damage is doubled until all of its
+    // effects have resolved
+    DoubleDamage,
+}
+
+impl Effect {
+    // Card text is built from these so it always matches what the card does
+    pub fn describe(&self) -> String {
+        match self {
+            Effect::DealDamage(amount) => format!("Deal {} damage", amount),
+            Effect::DealDamagePerRevealed(amount) => format!("Deal {} damage per\nrevealed enemy", amount),
+            Effect::Draw(1) => "Draw 1 card".to_string(),
+            Effect::Draw(count) => format!("Draw {} cards", count),
+            Effect::FlipRandomEnemy => "Reveal a\nrandom enemy".to_string(),
+            Effect::Reveal => "Reveal an enemy".to_string(),
+            Effect::Peek => "See a hidden\nenemy's intent".to_string(),
+            Effect::Heal(amount) => format!("Heal {}", amount),
+            Effect::GainBlock(amount) => format!("Gain {} block", amount),
+            Effect::GainBlockPerRevealed(amount) => format!("Gain {} block per\nrevealed enemy", amount),
+            Effect::GainEnergy(amount) => format!("Gain {} energy", amount),
+            Effect::ApplyStatus(status, stacks) => format!("Apply {} {}", stacks, status.name()),
+            Effect::GainStatus(status, stacks) => format!("Gain {} {}", stacks, status.name()),
+            Effect::DoubleDamage => "The card deals\ndouble damage".to_string(),
+        }
+    }
+}
+
+// Battle events content can react to, cards subscribe when played and stay
+// subscribed for the rest of the battle
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    OnCardPlayed,
+    OnCardDrawn,
+    OnDamageDealt,
+    OnDamageTaken,
+    OnEnemyRevealed,
+    OnTurnStart,
+    OnTurnEnd,
+    OnShuffle,
+    OnBattleStart,
+    OnVictory,
+}
+
+impl Trigger {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Trigger::OnCardPlayed => "When you play a card",
+            Trigger::OnCardDrawn => "When you draw a card",
+            Trigger::OnDamageDealt => "When you deal damage",
+            Trigger::OnDamageTaken => "When you take damage",
+            Trigger::OnEnemyRevealed => "When you reveal an enemy",
+            Trigger::OnTurnStart => "At the start of your turn",
+            Trigger::OnTurnEnd => "At the end of your turn",
+            Trigger::OnShuffle => "When you shuffle",
+            Trigger::OnBattleStart => "At the start of each battle",
+            Trigger::OnVictory => "When you win a battle",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TriggeredEffect {
+    pub trigger: Trigger,
+    pub effects: Vec<Effect>,
+}
+
+impl TriggeredEffect {
+    pub fn describe(&self) -> String {
+        let effects: Vec<String> = self.effects.iter().map(Effect::describe).collect();
+        format!("{}:\n{}", self.trigger.describe(), effects.join(", "))
+    }
+}
+
+pub fn describe_effects(effects: &[Effect]) -> String {
+    effects
+        .iter()
+        .map(Effect::describe)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
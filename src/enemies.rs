@@ -0,0 +1,46 @@
+// Enemies and the groups they are fought in, as read from the data files
+use crate::battle::intent::EnemyMove;
+use crate::{EncounterID, EnemyID};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct Enemy {
+    pub id: EnemyID,
+    pub name: String,
+    pub health: i8,
+    pub open: bool,
+    pub moves: Vec<EnemyMove>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    #[default]
+    Front,
+    Back,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    // the first few fights of an act
+    Easy,
+    Normal,
+    Elite,
+    Boss,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EncounterSlot {
+    pub enemy: EnemyID,
+    #[serde(default)]
+    pub row: Row,
+}
+
+// A group of enemies that are fought together, from `encounters.ron`
+#[derive(Debug, Deserialize, Clone)]
+pub struct Encounter {
+    pub id: EncounterID,
+    pub act: u8,
+    pub difficulty: Difficulty,
+    pub enemies: Vec<EncounterSlot>,
+}
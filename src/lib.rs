@@ -0,0 +1,30 @@
+// The game's rules and run state. Nothing in here touches raylib or the ECS
+// so battles and runs can be played out and tested without a window, the
+// binary mirrors this state onto entities to draw it
+use std::collections::BTreeMap;
+
+use crate::cards::Card;
+use crate::enemies::{Encounter, Enemy};
+use crate::relics::Relic;
+
+pub mod battle;
+pub mod cards;
+pub mod effects;
+pub mod enemies;
+pub mod map;
+pub mod relics;
+pub mod rewards;
+pub mod rng;
+pub mod run;
+pub mod save;
+
+// Set by the `id` key in the data files, saves refer to cards and enemies by
+// these so they must never change once released
+pub type CardID = String;
+pub type EnemyID = String;
+pub type EncounterID = String;
+pub type RelicID = String;
+pub type CardDB = BTreeMap<CardID, Card>;
+pub type EnemiesDB = BTreeMap<EnemyID, Enemy>;
+pub type EncountersDB = BTreeMap<EncounterID, Encounter>;
+pub type RelicDB = BTreeMap<RelicID, Relic>;
@@ -1,27 +1,45 @@
-use std::collections::BTreeMap;
-
 use raylib::prelude::*;
 use specs::prelude::*;
+use std::rc::Rc;
 
-use rand::Rng;
-
+use crate::battle::Battle;
+use crate::battle_scene::BattleScene;
 use crate::components::{
-    Active, Button, Card, Enemy, HealthBar, Mousehandler, Player, Position, Rectangle, Sprite,
+    Active, Button, EnemySlot, HandCard, HealthBar, Mousehandler, Player, Position, Rectangle,
+    Rotation, SceneOwned, Sprite, StatusBar, Tint, UiAction,
 };
 use crate::data_loading::{
-    generate_card_textures, generate_enemy_textures, get_cards_from_file, get_enemies_from_file,
+    generate_card_textures, generate_enemy_textures, load_assets,
+};
+use crate::rng::{seed_from_args, RunRng};
+use crate::run::Run;
+use crate::save::{
+    delete_save, read_save, save_exists, write_save, SaveError, SaveFile, SavedScene, SAVE_VERSION,
+};
+use crate::scene::{
+    spawn_button, DispatchScene, SceneChange, SceneStack, ScreenFade, Transition, BUTTON_HEIGHT,
+    BUTTON_WIDTH,
 };
 use crate::systems::{
-    BattleState, ButtonHandler, CardSelector, DamageQueue, DrawSys, EnemyAttacker, MainMenuDraw, DrawCardSys
+    ActionTimer, BattleActions, BattleEnd, BattleSync, ButtonHandler, CardSelector, DrawSys,
+    HandLayout, MainMenuDraw, MapDraw, RelicFlashSys, RelicFlashes, ResolveActions, RewardDraw,
+    RunSummaryDraw,
 };
 use crate::textures::Textures;
+use crate::tween::{DeltaTime, TweenSys, Tweens};
 
 pub mod battle_scene;
+pub mod card_render;
 pub mod components;
 pub mod data_loading;
+pub mod scene;
 pub mod systems;
 pub mod textures;
-pub mod cards;
+pub mod tween;
+
+// the rules live in the library so they can be tested without raylib
+use raylib_rs_ecs_template::{battle, cards, effects, enemies, map, relics, rng, run, save};
+pub use raylib_rs_ecs_template::{CardDB, CardID, EncountersDB, EnemiesDB, RelicDB, RelicID};
 
 pub const COLOUR: Color = Color::new(34, 32, 52, 255);
 pub const WIDTH: i32 = 1000;
@@ -30,15 +48,16 @@ pub const HEIGHT: i32 = 800;
 const C_WIDTH: i32 = 60;
 const C_HEIGHT: i32 = 80;
 
-pub type CardID = u64;
-pub type EnemyID = u64;
-pub type CardDB = BTreeMap<CardID, Card>;
-pub type EnemiesDB = BTreeMap<EnemyID, Enemy>;
+const PLAYER_HEALTH: i8 = 20;
 
-#[derive(Copy, Clone)]
-enum GameScenes {
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum GameScenes {
     MainMenu,
+    Map,
     Battle,
+    Reward,
+    GameOver,
+    Victory,
 }
 
 pub struct BattleFlags {
@@ -47,7 +66,24 @@ pub struct BattleFlags {
 }
 
 pub struct GameState {
-    current_scene: GameScenes
+    // applied by the scene stack once the frame's systems have run
+    transition: Option<Transition>,
+    continue_run: bool
+}
+
+impl GameState {
+    // The first request in a frame wins
+    pub fn change(&mut self, change: SceneChange) {
+        if self.transition.is_none() {
+            self.transition = Some(Transition { change, fade: false });
+        }
+    }
+
+    pub fn fade(&mut self, change: SceneChange) {
+        if self.transition.is_none() {
+            self.transition = Some(Transition { change, fade: true });
+        }
+    }
 }
 
 fn window_should_close(world: &World) -> bool {
@@ -55,11 +91,116 @@ fn window_should_close(world: &World) -> bool {
     rl.window_should_close()
 }
 
-fn get_game_scene(world: &World) -> GameScenes {
-    world.read_resource::<GameState>().current_scene
+fn update_delta_time(world: &World) {
+    let frame_time = world.read_resource::<RaylibHandle>().get_frame_time();
+    world.write_resource::<DeltaTime>().0 = frame_time;
+}
+
+fn take_transition(world: &World) -> Option<Transition> {
+    world.write_resource::<GameState>().transition.take()
+}
+
+fn save_run(world: &World, scene: GameScenes) {
+    let scene = match scene {
+        GameScenes::Map => SavedScene::Map,
+        GameScenes::Battle => SavedScene::Battle,
+        GameScenes::Reward => SavedScene::Reward,
+        GameScenes::MainMenu | GameScenes::GameOver | GameScenes::Victory => return,
+    };
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        scene,
+        rng: (*world.read_resource::<RunRng>()).clone(),
+        run: (*world.read_resource::<Run>()).clone(),
+        battle: match scene {
+            SavedScene::Battle => Some((*world.read_resource::<Battle>()).clone()),
+            _ => None,
+        },
+    };
+    if let Err(e) = write_save(&save) {
+        println!("Failed to save run: {}", e);
+    }
+}
+
+fn load_run(world: &mut World) -> Result<GameScenes, SaveError> {
+    let save = read_save(
+        &world.read_resource::<CardDB>(),
+        &world.read_resource::<EnemiesDB>(),
+        &world.read_resource::<RelicDB>(),
+    )?;
+    world.insert(save.rng);
+    world.insert(save.run);
+    if let Some(battle) = save.battle {
+        world.insert(battle);
+    }
+    Ok(match save.scene {
+        SavedScene::Map => GameScenes::Map,
+        SavedScene::Battle => GameScenes::Battle,
+        SavedScene::Reward => GameScenes::Reward,
+    })
+}
+
+// Coming back from a run, the next one gets a fresh seed
+fn setup_main_menu(world: &mut World, scene: GameScenes) {
+    if world.remove::<Run>().is_some() {
+        world.insert(RunRng::from_entropy());
+    }
+    let x = (WIDTH as f32 - BUTTON_WIDTH) / 2.;
+    let y = (HEIGHT as f32 - BUTTON_HEIGHT) / 2.;
+    spawn_button(world, scene, "Start Run", UiAction::StartRun, Position::new(x, y));
+    if save_exists() {
+        spawn_button(world, scene, "Continue", UiAction::ContinueRun, Position::new(x, y + 60.));
+    }
+    spawn_button(world, scene, "How to play", UiAction::HowToPlay, Position::new(x, y + 120.));
+}
+
+// Starts a run, or continues the saved one and reopens whatever scene it was
+// saved in on top of the map
+fn setup_map(world: &mut World, _scene: GameScenes) {
+    let continue_run = std::mem::replace(&mut world.write_resource::<GameState>().continue_run, false);
+    if !continue_run {
+        let run = {
+            let cards = world.read_resource::<CardDB>();
+            let mut rng = world.write_resource::<RunRng>();
+            Run::new(PLAYER_HEALTH, &cards, &mut rng)
+        };
+        world.insert(run);
+        return;
+    }
+    match load_run(world) {
+        Ok(GameScenes::Map) => {}
+        Ok(scene) => world.write_resource::<GameState>().change(SceneChange::Push(scene)),
+        Err(e) => {
+            println!("Failed to continue run: {}", e);
+            world.write_resource::<GameState>().change(SceneChange::Replace(GameScenes::MainMenu));
+        }
+    }
+}
+
+fn setup_reward(world: &mut World, scene: GameScenes) {
+    let x = (WIDTH as f32 - BUTTON_WIDTH) / 2.;
+    spawn_button(world, scene, "Skip", UiAction::SkipReward, Position::new(x, 500.));
+}
+
+// Nothing left to continue once a run is over
+fn setup_run_end(world: &mut World, scene: GameScenes) {
+    delete_save();
+    let x = (WIDTH as f32 - BUTTON_WIDTH) / 2.;
+    spawn_button(world, scene, "Main Menu", UiAction::MainMenu, Position::new(x, 550.));
 }
 
 fn main() {
+    let (cards, enemies, encounters, relics) = match load_assets() {
+        Ok(assets) => assets,
+        Err(errors) => {
+            println!("Failed to load assets:");
+            for e in errors {
+                println!("  {}", e);
+            }
+            return;
+        }
+    };
+
     let (mut rl, thread) = raylib::init()
         .size(WIDTH, HEIGHT)
         .title("Hello, World")
@@ -69,90 +210,135 @@ fn main() {
 
     let mut battle_textures = Textures::from_paths(vec!["card-back", "mouse-grab"], &mut rl, &thread);
     let menu_textures = Textures::from_paths(vec!["title"], &mut rl, &thread);
-    let cards = get_cards_from_file();
     generate_card_textures(&mut rl, &thread, &mut battle_textures, &cards);
-    let enemies = get_enemies_from_file();
     generate_enemy_textures(&mut rl, &thread, &mut battle_textures, &enemies);
+    // the reward screen draws the same card faces as battles
+    let battle_textures = Rc::new(battle_textures);
 
     let mut world = World::new();
     world.register::<Position>();
     world.register::<Rectangle>();
     world.register::<Sprite>();
-    world.register::<Card>();
-    world.register::<Enemy>();
     world.register::<Mousehandler>();
     world.register::<HealthBar>();
     world.register::<Player>();
     world.register::<Button>();
     world.register::<Active>();
+    world.register::<EnemySlot>();
+    world.register::<HandCard>();
+    world.register::<Rotation>();
+    world.register::<Tint>();
+    world.register::<Tweens>();
+    world.register::<StatusBar>();
+    world.register::<SceneOwned>();
+
+    let rng = match seed_from_args() {
+        Some(seed) => RunRng::new(seed),
+        None => RunRng::from_entropy(),
+    };
 
-    world.insert(BattleState::new(&cards));
+    world.insert(rng);
     world.insert(enemies);
+    world.insert(encounters);
+    world.insert(relics);
     world.insert(cards);
     world.insert(rl);
     world.insert(GameState {
-        current_scene: GameScenes::Battle
+        transition: None,
+        continue_run: false
     });
+    world.insert(ScreenFade::default());
+    world.insert(DeltaTime::default());
+    world.insert(ActionTimer::default());
+    world.insert(RelicFlashes::default());
 
+    let mut scenes = SceneStack::default();
+    scenes.add(
+        Box::new(DispatchScene {
+            id: GameScenes::MainMenu,
+            dispatcher: specs::DispatcherBuilder::new()
+                .with(ButtonHandler { scene: GameScenes::MainMenu }, "button_handler", &[])
+                .with_thread_local(MainMenuDraw { thread: thread.clone(), textures: menu_textures, reader: None })
+                .build(),
+            on_setup: Some(setup_main_menu),
+        }),
+        &mut world,
+    );
+    scenes.add(
+        Box::new(DispatchScene {
+            id: GameScenes::Map,
+            dispatcher: specs::DispatcherBuilder::new()
+                .with_thread_local(MapDraw { thread: thread.clone(), message: String::new() })
+                .build(),
+            on_setup: Some(setup_map),
+        }),
+        &mut world,
+    );
+    scenes.add(
+        Box::new(DispatchScene {
+            id: GameScenes::Reward,
+            dispatcher: specs::DispatcherBuilder::new()
+                .with(ButtonHandler { scene: GameScenes::Reward }, "button_handler", &[])
+                .with_thread_local(RewardDraw { thread: thread.clone(), textures: Rc::clone(&battle_textures), reader: None })
+                .build(),
+            on_setup: Some(setup_reward),
+        }),
+        &mut world,
+    );
+    for id in [GameScenes::GameOver, GameScenes::Victory].iter().copied() {
+        scenes.add(
+            Box::new(DispatchScene {
+                id,
+                dispatcher: specs::DispatcherBuilder::new()
+                    .with(ButtonHandler { scene: id }, "button_handler", &[])
+                    .with_thread_local(RunSummaryDraw { thread: thread.clone(), scene: id, reader: None })
+                    .build(),
+                on_setup: Some(setup_run_end),
+            }),
+            &mut world,
+        );
+    }
+    scenes.add(
+        Box::new(BattleScene {
+            dispatcher: specs::DispatcherBuilder::new()
+                .with_thread_local(DrawSys { thread, textures: battle_textures })
+                .with(
+                    CardSelector { dragging: None },
+                    "card_selector",
+                    &[],
+                )
+                .with(ButtonHandler { scene: GameScenes::Battle }, "button_handler", &[])
+                .with(BattleActions::default(), "battle_actions", &["button_handler"])
+                .with(ResolveActions, "resolve_actions", &["card_selector", "battle_actions"])
+                .with(BattleSync { hand: Vec::new() }, "battle_sync", &["resolve_actions"])
+                .with(HandLayout, "hand_layout", &["battle_sync"])
+                .with(TweenSys, "tweens", &["battle_sync"])
+                .with(RelicFlashSys, "relic_flashes", &["resolve_actions"])
+                .with(BattleEnd, "battle_end", &["battle_sync"])
+                .build(),
+        }),
+        &mut world,
+    );
 
-    let mut menu_dispatcher = specs::DispatcherBuilder::new()
-        .with_thread_local(MainMenuDraw { thread: thread.clone(), textures: menu_textures })
-        .with(ButtonHandler, "button_handler", &[])
-        .build();
-
-    let mut battle_dispatcher = specs::DispatcherBuilder::new()
-        .with_thread_local(DrawSys { thread, textures: battle_textures })
-        .with(
-            CardSelector {
-                selected: None,
-                dragging: None,
-            },
-            "card_selector",
-            &[],
-        )
-        .with(DamageQueue, "damage_queue", &["card_selector"])
-        .with(EnemyAttacker, "enemy_attacker", &[])
-        .with(ButtonHandler, "button_handler", &[])
-        .with(DrawCardSys, "draw_cards", &[])
-        .build();
-
-
-    world
-        .create_entity()
-        .with(HealthBar::new(20))
-        .with(Position { x: 50., y: 100. })
-        .with(Rectangle {
-            width: 200.,
-            height: 50.,
-        })
-        .with(Player::default())
-        .build();
-
-    world
-        .create_entity()
-        .with(Position {
-            x: (WIDTH - 200) as f32,
-            y: (HEIGHT - 100) as f32,
-        })
-        .with(Rectangle {
-            width: 150.,
-            height: 50.,
-        })
-        .with(Button::new(
-            "End Turn",
-            "end_turn"
-        ))
-        .build();
-
-    battle_dispatcher.setup(&mut world);
-    menu_dispatcher.setup(&mut world);
-
+    world.write_resource::<GameState>().change(SceneChange::Push(GameScenes::MainMenu));
     loop {
-        match get_game_scene(&world) {
-            GameScenes::MainMenu => menu_dispatcher.dispatch(&world),
-            GameScenes::Battle => battle_dispatcher.dispatch(&world),
+        // a scene's setup can ask for another change, e.g. continuing a run
+        // straight into its saved battle
+        let mut changed = false;
+        while let Some(transition) = take_transition(&world) {
+            scenes.apply(&mut world, transition);
+            changed = true;
+        }
+        if let (true, Some(scene)) = (changed, scenes.current()) {
+            save_run(&world, scene);
         }
+
+        update_delta_time(&world);
+        scenes.dispatch(&world);
         if window_should_close(&world) {
+            if let Some(scene) = scenes.current() {
+                save_run(&world, scene);
+            }
             break;
         }
         world.maintain();
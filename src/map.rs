@@ -0,0 +1,213 @@
+use crate::rng::{RunRng, Stream};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const MAP_LAYERS: usize = 12;
+const MIN_WIDTH: usize = 2;
+const MAX_WIDTH: usize = 4;
+const TREASURE_LAYER: usize = 6;
+// no elites or rests this early on
+const SAFE_LAYERS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeKind {
+    Battle,
+    Elite,
+    Rest,
+    Shop,
+    Event,
+    Treasure,
+    Boss,
+}
+
+impl NodeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            NodeKind::Battle => "B",
+            NodeKind::Elite => "E",
+            NodeKind::Rest => "R",
+            NodeKind::Shop => "$",
+            NodeKind::Event => "?",
+            NodeKind::Treasure => "T",
+            NodeKind::Boss => "Boss",
+        }
+    }
+
+    pub fn is_battle(self) -> bool {
+        match self {
+            NodeKind::Battle | NodeKind::Elite | NodeKind::Boss => true,
+            NodeKind::Rest | NodeKind::Shop | NodeKind::Event | NodeKind::Treasure => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeRef {
+    pub layer: usize,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapNode {
+    pub kind: NodeKind,
+    // indices of the nodes this one leads to in the next layer
+    pub next: Vec<usize>,
+}
+
+// Layered DAG, layer 0 is where a run starts and the last layer is the boss
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMap {
+    pub layers: Vec<Vec<MapNode>>,
+}
+
+impl RunMap {
+    pub fn generate(rng: &mut RunRng) -> Self {
+        let rng = rng.stream(Stream::Map);
+        let mut layers: Vec<Vec<MapNode>> = Vec::new();
+        for layer in 0..MAP_LAYERS {
+            let width = if layer == MAP_LAYERS - 1 {
+                1
+            } else {
+                rng.gen_range(MIN_WIDTH, MAX_WIDTH + 1)
+            };
+            let nodes = (0..width)
+                .map(|_| MapNode {
+                    kind: roll_kind(layer, rng),
+                    next: Vec::new(),
+                })
+                .collect();
+            layers.push(nodes);
+        }
+
+        for layer in 0..MAP_LAYERS - 1 {
+            let from_width = layers[layer].len();
+            let to_width = layers[layer + 1].len();
+            for (index, node) in layers[layer].iter_mut().enumerate() {
+                let closest = scale_index(index, from_width, to_width);
+                let mut next = vec![closest];
+                if closest + 1 < to_width && rng.gen_bool(0.5) {
+                    next.push(closest + 1);
+                }
+                node.next = next;
+            }
+            // every node needs a way in
+            for target in 0..to_width {
+                let reachable = layers[layer].iter().any(|node| node.next.contains(&target));
+                if !reachable {
+                    let from = scale_index(target, to_width, from_width);
+                    layers[layer][from].next.push(target);
+                    layers[layer][from].next.sort();
+                }
+            }
+        }
+        RunMap { layers }
+    }
+
+    pub fn node(&self, node: NodeRef) -> &MapNode {
+        &self.layers[node.layer][node.index]
+    }
+
+    // Where the player can go from `from`, every node of the first layer when
+    // the run hasn't started yet
+    pub fn next_nodes(&self, from: Option<NodeRef>) -> Vec<NodeRef> {
+        match from {
+            None => (0..self.layers[0].len())
+                .map(|index| NodeRef { layer: 0, index })
+                .collect(),
+            Some(from) if from.layer + 1 < self.layers.len() => self
+                .node(from)
+                .next
+                .iter()
+                .map(|index| NodeRef {
+                    layer: from.layer + 1,
+                    index: *index,
+                })
+                .collect(),
+            Some(_) => Vec::new(),
+        }
+    }
+}
+
+fn scale_index(index: usize, from_width: usize, to_width: usize) -> usize {
+    if from_width <= 1 {
+        return (to_width - 1) / 2;
+    }
+    (index * (to_width - 1) + (from_width - 1) / 2) / (from_width - 1)
+}
+
+fn roll_kind<R: Rng>(layer: usize, rng: &mut R) -> NodeKind {
+    if layer == 0 {
+        return NodeKind::Battle;
+    }
+    if layer == MAP_LAYERS - 1 {
+        return NodeKind::Boss;
+    }
+    if layer == MAP_LAYERS - 2 {
+        return NodeKind::Rest;
+    }
+    if layer == TREASURE_LAYER {
+        return NodeKind::Treasure;
+    }
+    let early = layer < SAFE_LAYERS;
+    let weights = [
+        (NodeKind::Battle, 45),
+        (NodeKind::Event, 20),
+        (NodeKind::Shop, 13),
+        (NodeKind::Elite, if early { 0 } else { 12 }),
+        (NodeKind::Rest, if early { 0 } else { 10 }),
+    ];
+    let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0, total);
+    for (kind, weight) in weights.iter() {
+        if roll < *weight {
+            return *kind;
+        }
+        roll -= weight;
+    }
+    NodeKind::Battle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_node_can_be_reached_from_the_start() {
+        for seed in 0..50 {
+            let map = RunMap::generate(&mut RunRng::new(seed));
+            let mut reachable = map.next_nodes(None);
+            for layer in 1..map.layers.len() {
+                let mut next: Vec<NodeRef> = reachable
+                    .iter()
+                    .filter(|node| node.layer == layer - 1)
+                    .flat_map(|node| map.next_nodes(Some(*node)))
+                    .collect();
+                next.sort_by_key(|node| node.index);
+                next.dedup();
+                assert_eq!(next.len(), map.layers[layer].len(), "seed {} layer {}", seed, layer);
+                reachable.extend(next);
+            }
+        }
+    }
+
+    #[test]
+    fn the_last_layer_is_a_single_boss() {
+        for seed in 0..50 {
+            let map = RunMap::generate(&mut RunRng::new(seed));
+            assert_eq!(map.layers.len(), MAP_LAYERS);
+            let last = map.layers.last().unwrap();
+            assert_eq!(last.len(), 1);
+            assert_eq!(last[0].kind, NodeKind::Boss);
+            let bosses = map.layers.iter().flatten().filter(|node| node.kind == NodeKind::Boss);
+            assert_eq!(bosses.count(), 1);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_map() {
+        let map = RunMap::generate(&mut RunRng::new(7));
+        assert_eq!(map, RunMap::generate(&mut RunRng::new(7)));
+        assert_ne!(map, RunMap::generate(&mut RunRng::new(8)));
+    }
+}
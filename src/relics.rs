@@ -0,0 +1,56 @@
+// Passive items kept for the whole run, each one listens for a battle event
+// the same way cards with triggers do
+use crate::effects::{Effect, Trigger};
+use crate::rng::{RunRng, Stream};
+use crate::{RelicDB, RelicID};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Relic {
+    pub id: RelicID,
+    pub name: String,
+    // icons are drawn as a circle of this colour
+    pub color: (u8, u8, u8),
+    pub trigger: Trigger,
+    // only fires on every `every`th time the trigger happens
+    #[serde(default = "every_time")]
+    pub every: u32,
+    pub effects: Vec<Effect>,
+}
+
+fn every_time() -> u32 {
+    1
+}
+
+impl Relic {
+    pub fn description(&self) -> String {
+        let effects: Vec<String> = self.effects.iter().map(Effect::describe).collect();
+        let effects = effects.join(", ").replace('\n', " ");
+        if self.every > 1 {
+            format!("{} {} times:\n{}", self.trigger.describe(), self.every, effects)
+        } else {
+            format!("{}:\n{}", self.trigger.describe(), effects)
+        }
+    }
+}
+
+// A relic the player has, the counter carries over between battles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnedRelic {
+    pub id: RelicID,
+    pub counter: u32,
+}
+
+// A random relic the player doesn't have yet, none once they have them all
+pub fn roll_relic(relics: &RelicDB, owned: &[OwnedRelic], rng: &mut RunRng) -> Option<RelicID> {
+    let pool: Vec<&RelicID> = relics
+        .keys()
+        .filter(|id| !owned.iter().any(|relic| relic.id == **id))
+        .collect();
+    if pool.is_empty() {
+        return None;
+    }
+    Some(pool[rng.stream(Stream::Rewards).gen_range(0, pool.len())].clone())
+}
@@ -0,0 +1,115 @@
+use crate::cards::Rarity;
+use crate::rng::{RunRng, Stream};
+use crate::{CardDB, CardID};
+
+use rand::Rng;
+
+pub const CARD_REWARD_CHOICES: usize = 3;
+const RARITIES: [Rarity; 3] = [Rarity::Common, Rarity::Uncommon, Rarity::Rare];
+
+fn rarity_weight(rarity: Rarity) -> u32 {
+    match rarity {
+        Rarity::Common => 60,
+        Rarity::Uncommon => 30,
+        Rarity::Rare => 10,
+    }
+}
+
+// Rolls a rarity first and then a card of that rarity, never offering the same
+// card twice unless the database is too small not to
+pub fn roll_card_rewards(cards: &CardDB, count: usize, rng: &mut RunRng) -> Vec<CardID> {
+    let rng = rng.stream(Stream::Rewards);
+    let mut offered: Vec<CardID> = Vec::new();
+    for _ in 0..count {
+        let pool: Vec<(CardID, Rarity)> = cards
+            .iter()
+            .filter(|(id, _)| !offered.contains(id) || offered.len() >= cards.len())
+            .map(|(id, card)| (id.clone(), card.rarity))
+            .collect();
+        if pool.is_empty() {
+            break;
+        }
+        let rarities: Vec<Rarity> = RARITIES
+            .iter()
+            .copied()
+            .filter(|rarity| pool.iter().any(|(_, card_rarity)| card_rarity == rarity))
+            .collect();
+        let total: u32 = rarities.iter().map(|rarity| rarity_weight(*rarity)).sum();
+        let mut roll = rng.gen_range(0, total);
+        let mut rarity = rarities[0];
+        for candidate in rarities {
+            let weight = rarity_weight(candidate);
+            if roll < weight {
+                rarity = candidate;
+                break;
+            }
+            roll -= weight;
+        }
+        let of_rarity: Vec<CardID> = pool
+            .iter()
+            .filter(|(_, card_rarity)| *card_rarity == rarity)
+            .map(|(id, _)| id.clone())
+            .collect();
+        offered.push(of_rarity[rng.gen_range(0, of_rarity.len())].clone());
+    }
+    offered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::Card;
+
+    fn cards(per_rarity: usize) -> CardDB {
+        RARITIES
+            .iter()
+            .flat_map(|rarity| {
+                (0..per_rarity).map(move |index| {
+                    let id = format!("{:?}_{}", rarity, index);
+                    let card = Card {
+                        id: id.clone(),
+                        rarity: *rarity,
+                        ..Card::default()
+                    };
+                    (id, card)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rewards_are_distinct() {
+        let cards = cards(2);
+        let mut rng = RunRng::new(5);
+        for _ in 0..200 {
+            let mut offered = roll_card_rewards(&cards, CARD_REWARD_CHOICES, &mut rng);
+            assert_eq!(offered.len(), CARD_REWARD_CHOICES);
+            offered.sort();
+            offered.dedup();
+            assert_eq!(offered.len(), CARD_REWARD_CHOICES);
+        }
+    }
+
+    #[test]
+    fn small_databases_still_fill_the_reward() {
+        let cards: CardDB = cards(1).into_iter().take(2).collect();
+        let offered = roll_card_rewards(&cards, CARD_REWARD_CHOICES, &mut RunRng::new(5));
+        assert_eq!(offered.len(), CARD_REWARD_CHOICES);
+        assert!(cards.keys().all(|id| offered.contains(id)));
+    }
+
+    #[test]
+    fn rarities_follow_their_weights() {
+        let cards = cards(5);
+        let mut rng = RunRng::new(5);
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            let first = &roll_card_rewards(&cards, 1, &mut rng)[0];
+            let rarity = RARITIES.iter().position(|rarity| cards[first].rarity == *rarity).unwrap();
+            counts[rarity] += 1;
+        }
+        assert!((1650..1950).contains(&counts[0]), "{:?}", counts);
+        assert!((750..1050).contains(&counts[1]), "{:?}", counts);
+        assert!((200..400).contains(&counts[2]), "{:?}", counts);
+    }
+}
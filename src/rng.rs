@@ -0,0 +1,144 @@
+use rand::{Error, RngCore};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    // shuffles in battle
+    Deck,
+    // the cards a run starts with
+    StartingDeck,
+    EnemyAi,
+    Rewards,
+    CardEffects,
+    Map,
+}
+
+impl Stream {
+    fn salt(self) -> u64 {
+        match self {
+            Stream::Deck => 1,
+            Stream::EnemyAi => 2,
+            Stream::Rewards => 3,
+            Stream::CardEffects => 4,
+            Stream::Map => 5,
+            Stream::StartingDeck => 6,
+        }
+    }
+}
+
+// splitmix64, small enough to serialize and stable across rand versions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamRng {
+    state: u64,
+}
+
+impl StreamRng {
+    pub fn new(seed: u64, stream: Stream) -> Self {
+        let mut rng = StreamRng {
+            state: seed ^ stream.salt().wrapping_mul(0xD1B5_4A32_D192_ED03),
+        };
+        // throw away the first output so nearby seeds don't start out correlated
+        rng.next_u64();
+        rng
+    }
+}
+
+impl RngCore for StreamRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Every random roll in a run goes through one of these streams, so a run can be
+// replayed from its seed and drawing more cards never changes what enemies do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRng {
+    pub seed: u64,
+    deck: StreamRng,
+    enemy_ai: StreamRng,
+    rewards: StreamRng,
+    card_effects: StreamRng,
+    map: StreamRng,
+    starting_deck: StreamRng,
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        RunRng {
+            seed,
+            deck: StreamRng::new(seed, Stream::Deck),
+            enemy_ai: StreamRng::new(seed, Stream::EnemyAi),
+            rewards: StreamRng::new(seed, Stream::Rewards),
+            card_effects: StreamRng::new(seed, Stream::CardEffects),
+            map: StreamRng::new(seed, Stream::Map),
+            starting_deck: StreamRng::new(seed, Stream::StartingDeck),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        RunRng::new(rand::random())
+    }
+
+    pub fn stream(&mut self, stream: Stream) -> &mut StreamRng {
+        match stream {
+            Stream::Deck => &mut self.deck,
+            Stream::EnemyAi => &mut self.enemy_ai,
+            Stream::Rewards => &mut self.rewards,
+            Stream::CardEffects => &mut self.card_effects,
+            Stream::Map => &mut self.map,
+            Stream::StartingDeck => &mut self.starting_deck,
+        }
+    }
+}
+
+pub fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|seed| seed.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolls(rng: &mut RunRng, stream: Stream) -> Vec<u64> {
+        (0..5).map(|_| rng.stream(stream).next_u64()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_rolls_the_same_numbers() {
+        assert_eq!(rolls(&mut RunRng::new(7), Stream::Deck), rolls(&mut RunRng::new(7), Stream::Deck));
+        assert_ne!(rolls(&mut RunRng::new(7), Stream::Deck), rolls(&mut RunRng::new(8), Stream::Deck));
+    }
+
+    #[test]
+    fn rolling_one_stream_leaves_the_others_alone() {
+        let expected = rolls(&mut RunRng::new(7), Stream::Deck);
+        let mut rng = RunRng::new(7);
+        rolls(&mut rng, Stream::StartingDeck);
+        rolls(&mut rng, Stream::EnemyAi);
+        assert_eq!(rolls(&mut rng, Stream::Deck), expected);
+    }
+}
@@ -0,0 +1,336 @@
+// Everything that carries over between map nodes, a battle is built from this
+// when it starts and writes the player's health back when it ends
+use crate::battle::{Battle, BattleEnemy, Fighter, Outcome};
+use crate::cards::generate_intial_deck;
+use crate::enemies::{Difficulty, Encounter};
+use crate::map::{NodeKind, NodeRef, RunMap};
+use crate::rewards::{roll_card_rewards, CARD_REWARD_CHOICES};
+use crate::rng::{RunRng, Stream};
+use crate::relics::{roll_relic, OwnedRelic};
+use crate::{CardDB, CardID, EncountersDB, EnemiesDB, RelicDB};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const SHOP_CARD_PRICE: u32 = 50;
+// the map is a single act for now
+pub const ACT: u8 = 1;
+// fights on the first few layers of the map are easy ones
+const EASY_LAYERS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub max_health: i8,
+    pub health: i8,
+    pub deck: Vec<CardID>,
+    pub gold: u32,
+    pub map: RunMap,
+    pub position: Option<NodeRef>,
+    // offered after the last battle, empty once one is picked or skipped
+    pub card_rewards: Vec<CardID>,
+    pub relics: Vec<OwnedRelic>,
+    pub stats: RunStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub floors_cleared: u32,
+    pub damage_dealt: u32,
+    pub cards_played: u32,
+    pub cause_of_death: Option<String>,
+}
+
+impl Run {
+    pub fn new(max_health: i8, cards: &CardDB, rng: &mut RunRng) -> Self {
+        Run {
+            max_health,
+            health: max_health,
+            deck: generate_intial_deck(cards, rng),
+            gold: 0,
+            map: RunMap::generate(rng),
+            position: None,
+            card_rewards: Vec::new(),
+            relics: Vec::new(),
+            stats: RunStats::default(),
+        }
+    }
+
+    pub fn next_nodes(&self) -> Vec<NodeRef> {
+        self.map.next_nodes(self.position)
+    }
+
+    pub fn current_kind(&self) -> Option<NodeKind> {
+        self.position.map(|node| self.map.node(node).kind)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.health <= 0 || self.is_won()
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.health > 0 && self.current_kind() == Some(NodeKind::Boss)
+    }
+
+    pub fn enter(&mut self, node: NodeRef) -> Option<NodeKind> {
+        if !self.next_nodes().contains(&node) {
+            return None;
+        }
+        self.position = Some(node);
+        self.current_kind()
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        match self.current_kind() {
+            Some(NodeKind::Elite) => Difficulty::Elite,
+            Some(NodeKind::Boss) => Difficulty::Boss,
+            _ if self.position.map_or(0, |node| node.layer) < EASY_LAYERS => Difficulty::Easy,
+            _ => Difficulty::Normal,
+        }
+    }
+
+    // Loading the data checks there is an encounter for every difficulty in
+    // the act and that their enemies exist, loading a save checks its relics
+    pub fn start_battle(
+        &self,
+        enemies: &EnemiesDB,
+        encounters: &EncountersDB,
+        relics: &RelicDB,
+        rng: &mut RunRng,
+    ) -> Battle {
+        let difficulty = self.difficulty();
+        let options: Vec<&Encounter> = encounters
+            .values()
+            .filter(|encounter| encounter.act == ACT && encounter.difficulty == difficulty)
+            .collect();
+        let encounter = options[rng.stream(Stream::Map).gen_range(0, options.len())];
+        let picked: Vec<BattleEnemy> = encounter
+            .enemies
+            .iter()
+            .map(|slot| BattleEnemy::new(&enemies[&slot.enemy], slot.row))
+            .collect();
+        let mut player = Fighter::new(self.max_health);
+        player.health = self.health;
+        let mut battle = Battle::new(player, self.deck.clone(), picked);
+        for owned in &self.relics {
+            battle.add_relic(&relics[&owned.id], owned.counter);
+        }
+        battle.start();
+        battle
+    }
+
+    pub fn finish_battle(&mut self, battle: &Battle, cards: &CardDB, rng: &mut RunRng) {
+        self.health = battle.player.health;
+        self.stats.damage_dealt += battle.stats.damage_dealt;
+        self.stats.cards_played += battle.stats.cards_played;
+        for (id, counter) in battle.relic_counters() {
+            if let Some(owned) = self.relics.iter_mut().find(|owned| owned.id == *id) {
+                owned.counter = counter;
+            }
+        }
+        match battle.outcome() {
+            Some(Outcome::Won) => {
+                self.stats.floors_cleared += 1;
+                if !self.is_finished() {
+                    self.card_rewards = roll_card_rewards(cards, CARD_REWARD_CHOICES, rng);
+                }
+            }
+            Some(Outcome::Lost) => {
+                self.stats.cause_of_death = battle.stats.last_damage_source.clone();
+            }
+            None => {}
+        }
+    }
+
+    pub fn take_reward(&mut self, choice: Option<CardID>) {
+        if let Some(card_id) = choice.filter(|card_id| self.card_rewards.contains(card_id)) {
+            self.deck.push(card_id);
+        }
+        self.card_rewards.clear();
+    }
+
+    pub fn heal(&mut self, amount: i8) {
+        self.health = std::cmp::min(self.max_health, self.health.saturating_add(amount));
+    }
+
+    fn random_card(cards: &CardDB, rng: &mut RunRng) -> CardID {
+        let index = rng.stream(Stream::Rewards).gen_range(0, cards.len());
+        cards.keys().nth(index).unwrap().clone()
+    }
+
+    // Resolves the nodes that don't need their own scene, returns what happened
+    pub fn resolve_node(&mut self, cards: &CardDB, relics: &RelicDB, rng: &mut RunRng) -> String {
+        self.stats.floors_cleared += 1;
+        match self.current_kind() {
+            Some(NodeKind::Rest) => {
+                let amount = std::cmp::max(1, self.max_health * 3 / 10);
+                self.heal(amount);
+                format!("You rest and heal {}", amount)
+            }
+            // gold once every relic has been found
+            Some(NodeKind::Treasure) => {
+                if let Some(id) = roll_relic(relics, &self.relics, rng) {
+                    let message = format!("You find {}", relics[&id].name);
+                    self.relics.push(OwnedRelic { id, counter: 0 });
+                    return message;
+                }
+                let gold = rng.stream(Stream::Rewards).gen_range(50, 101);
+                self.gold += gold;
+                format!("You find {} gold", gold)
+            }
+            Some(NodeKind::Shop) => {
+                if self.gold < SHOP_CARD_PRICE {
+                    return "You can't afford anything".to_string();
+                }
+                self.gold -= SHOP_CARD_PRICE;
+                let card_id = Run::random_card(cards, rng);
+                let message = format!("You buy {}", cards[&card_id].name);
+                self.deck.push(card_id);
+                message
+            }
+            Some(NodeKind::Event) => match rng.stream(Stream::Rewards).gen_range(0, 4) {
+                0 => {
+                    self.heal(5);
+                    "A spring heals you for 5".to_string()
+                }
+                1 => {
+                    self.gold += 30;
+                    "You find 30 gold".to_string()
+                }
+                2 => {
+                    self.health = std::cmp::max(1, self.health - 5);
+                    "A trap hits you for 5".to_string()
+                }
+                _ => {
+                    let card_id = Run::random_card(cards, rng);
+                    let message = format!("A stranger gives you {}", cards[&card_id].name);
+                    self.deck.push(card_id);
+                    message
+                }
+            },
+            _ => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::intent::{EnemyMove, MoveAction};
+    use crate::battle::Target;
+    use crate::cards::{Card, Cost};
+    use crate::effects::{Effect, Trigger};
+    use crate::enemies::{EncounterSlot, Enemy, Row};
+    use crate::relics::Relic;
+    use crate::RelicID;
+
+    fn cards() -> CardDB {
+        let strike = Card {
+            id: "strike".to_string(),
+            name: "Strike".to_string(),
+            cost: Cost::Fixed(0),
+            effects: vec![Effect::DealDamage(6)],
+            ..Card::default()
+        };
+        vec![("strike".to_string(), strike)].into_iter().collect()
+    }
+
+    fn enemies() -> EnemiesDB {
+        let dummy = Enemy {
+            id: "dummy".to_string(),
+            name: "Dummy".to_string(),
+            health: 12,
+            open: true,
+            moves: vec![EnemyMove {
+                name: "Wait".to_string(),
+                weight: 1,
+                max_in_a_row: usize::MAX,
+                action: MoveAction::Defend(0),
+            }],
+        };
+        vec![("dummy".to_string(), dummy)].into_iter().collect()
+    }
+
+    fn encounters() -> EncountersDB {
+        let encounter = Encounter {
+            id: "dummy".to_string(),
+            act: ACT,
+            difficulty: Difficulty::Easy,
+            enemies: vec![EncounterSlot {
+                enemy: "dummy".to_string(),
+                row: Row::Front,
+            }],
+        };
+        vec![("dummy".to_string(), encounter)].into_iter().collect()
+    }
+
+    fn relic(id: &str, trigger: Trigger, every: u32, effects: Vec<Effect>) -> (RelicID, Relic) {
+        let relic = Relic {
+            id: id.to_string(),
+            name: id.to_string(),
+            color: (0, 0, 0),
+            trigger,
+            every,
+            effects,
+        };
+        (id.to_string(), relic)
+    }
+
+    fn relics() -> RelicDB {
+        vec![
+            relic("war_drum", Trigger::OnCardPlayed, 3, vec![Effect::DoubleDamage]),
+            relic("healing_herb", Trigger::OnVictory, 1, vec![Effect::Heal(6)]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    // Plays strikes at the dummy until the battle is won
+    fn win_battle(run: &mut Run, rng: &mut RunRng) -> usize {
+        let cards = cards();
+        let mut battle = run.start_battle(&enemies(), &encounters(), &relics(), rng);
+        battle.resolve_all(rng);
+        let mut played = 0;
+        while battle.outcome().is_none() {
+            battle.play_card(&cards, "strike", Target::Enemy(0)).unwrap();
+            battle.resolve_all(rng);
+            played += 1;
+        }
+        run.finish_battle(&battle, &cards, rng);
+        played
+    }
+
+    fn run_with_relics(rng: &mut RunRng) -> Run {
+        let mut run = Run::new(30, &cards(), rng);
+        for id in relics().keys() {
+            run.relics.push(OwnedRelic { id: id.clone(), counter: 0 });
+        }
+        run
+    }
+
+    #[test]
+    fn relic_counters_carry_over_between_battles() {
+        let mut rng = RunRng::new(2);
+        let mut run = run_with_relics(&mut rng);
+        assert_eq!(win_battle(&mut run, &mut rng), 2);
+        let drum = run.relics.iter().find(|owned| owned.id == "war_drum").unwrap();
+        assert_eq!(drum.counter, 2);
+
+        // the third card of the run is doubled and kills the dummy alone
+        assert_eq!(win_battle(&mut run, &mut rng), 1);
+        let drum = run.relics.iter().find(|owned| owned.id == "war_drum").unwrap();
+        assert_eq!(drum.counter, 0);
+    }
+
+    #[test]
+    fn victory_healing_reaches_the_run() {
+        let mut rng = RunRng::new(2);
+        let mut run = run_with_relics(&mut rng);
+        run.health = 20;
+        win_battle(&mut run, &mut rng);
+        assert_eq!(run.health, 26);
+
+        run.health = 28;
+        win_battle(&mut run, &mut rng);
+        assert_eq!(run.health, run.max_health);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::battle::{Battle, Source};
+use crate::rng::RunRng;
+use crate::run::Run;
+use crate::{CardDB, EnemiesDB, RelicDB};
+
+use ron::de::from_str;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub const SAVE_PATH: &str = "save.ron";
+// Bump whenever a saved type changes shape, older saves are refused rather
+// than half loaded
+pub const SAVE_VERSION: u32 = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SavedScene {
+    Map,
+    Battle,
+    Reward,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub scene: SavedScene,
+    pub rng: RunRng,
+    pub run: Run,
+    pub battle: Option<Battle>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(ron::ser::Error),
+    Parse(ron::de::Error),
+    Version(u32),
+    // what kind of thing the id is for and the id itself
+    UnknownId(&'static str, String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "could not access {}: {}", SAVE_PATH, e),
+            SaveError::Serialize(e) => write!(f, "could not write save: {}", e),
+            SaveError::Parse(e) => write!(f, "could not read {}: {}", SAVE_PATH, e),
+            SaveError::Version(version) => write!(
+                f,
+                "{} is version {} but this build only reads version {}",
+                SAVE_PATH, version, SAVE_VERSION
+            ),
+            SaveError::UnknownId(kind, id) => write!(
+                f,
+                "{} has {} {:?} which is no longer in the data files",
+                SAVE_PATH, kind, id
+            ),
+        }
+    }
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+pub fn write_save(save: &SaveFile) -> Result<(), SaveError> {
+    let text = to_string_pretty(save, PrettyConfig::default()).map_err(SaveError::Serialize)?;
+    fs::write(SAVE_PATH, text).map_err(SaveError::Io)
+}
+
+pub fn read_save(cards: &CardDB, enemies: &EnemiesDB, relics: &RelicDB) -> Result<SaveFile, SaveError> {
+    let text = fs::read_to_string(SAVE_PATH).map_err(SaveError::Io)?;
+    let save = parse_save(&text)?;
+    check_ids(&save, cards, enemies, relics)?;
+    Ok(save)
+}
+
+// Checks the version first so an old save gives a useful error instead of
+// whatever field happened to change
+fn parse_save(text: &str) -> Result<SaveFile, SaveError> {
+    match saved_version(text) {
+        Some(version) if version != SAVE_VERSION => Err(SaveError::Version(version)),
+        _ => from_str(text).map_err(SaveError::Parse),
+    }
+}
+
+// Ids in the save are looked up in the data files later on, one that has been
+// removed since is refused here instead of panicking then
+fn check_ids(save: &SaveFile, cards: &CardDB, enemies: &EnemiesDB, relics: &RelicDB) -> Result<(), SaveError> {
+    let run = &save.run;
+    let mut card_ids: Vec<&String> = run.deck.iter().chain(&run.card_rewards).collect();
+    let mut enemy_ids = Vec::new();
+    let mut relic_ids: Vec<&String> = run.relics.iter().map(|owned| &owned.id).collect();
+    if let Some(battle) = &save.battle {
+        let deck = &battle.deck;
+        card_ids.extend(deck.draw_pile.iter().chain(&deck.hand).chain(&deck.discard));
+        enemy_ids.extend(battle.enemies.iter().map(|enemy| &enemy.id));
+        for listener in &battle.listeners {
+            match &listener.source {
+                Source::Card(id) => card_ids.push(id),
+                Source::Relic(id) => relic_ids.push(id),
+            }
+        }
+    }
+    check_in_db("card", card_ids, cards)?;
+    check_in_db("enemy", enemy_ids, enemies)?;
+    check_in_db("relic", relic_ids, relics)
+}
+
+fn check_in_db<T>(kind: &'static str, ids: Vec<&String>, db: &BTreeMap<String, T>) -> Result<(), SaveError> {
+    match ids.into_iter().find(|id| !db.contains_key(*id)) {
+        Some(id) => Err(SaveError::UnknownId(kind, id.clone())),
+        None => Ok(()),
+    }
+}
+
+// `version` is always written first, so it is read straight from the text.
+// Deserializing only that field would make ron skip the others as signed
+// numbers, which fails on rng states above i64::MAX
+fn saved_version(text: &str) -> Option<u32> {
+    let start = text.find("version:")? + "version:".len();
+    let digits: String = text[start..]
+        .trim_start()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+pub fn delete_save() {
+    if save_exists() {
+        let _res = fs::remove_file(SAVE_PATH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::Card;
+    use crate::relics::OwnedRelic;
+
+    fn cards() -> CardDB {
+        let card = Card {
+            id: "strike".to_string(),
+            ..Card::default()
+        };
+        vec![(card.id.clone(), card)].into_iter().collect()
+    }
+
+    fn new_save(cards: &CardDB) -> SaveFile {
+        let mut rng = RunRng::new(u64::MAX);
+        let run = Run::new(20, cards, &mut rng);
+        SaveFile {
+            version: SAVE_VERSION,
+            scene: SavedScene::Map,
+            rng,
+            run,
+            battle: None,
+        }
+    }
+
+    #[test]
+    fn saves_load_with_rng_states_above_i64_max() {
+        let save = new_save(&cards());
+        let text = to_string_pretty(&save, PrettyConfig::default()).unwrap();
+        let loaded = parse_save(&text).unwrap();
+        assert_eq!(loaded.rng, save.rng);
+        assert_eq!(loaded.run.deck, save.run.deck);
+    }
+
+    #[test]
+    fn old_saves_report_their_version() {
+        let text = "(\n    version: 3,\n    scene: Map,\n    rng: (seed: 18446744073709551615),\n)";
+        match parse_save(text) {
+            Err(SaveError::Version(3)) => {}
+            other => panic!("expected a version error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn saves_without_a_version_fail_to_parse() {
+        assert_eq!(saved_version("(scene: Map)"), None);
+        assert!(matches!(parse_save("(scene: Map)"), Err(SaveError::Parse(_))));
+    }
+
+    #[test]
+    fn saves_with_ids_missing_from_the_data_are_refused() {
+        let cards = cards();
+        let mut save = new_save(&cards);
+        assert!(check_ids(&save, &cards, &EnemiesDB::new(), &RelicDB::new()).is_ok());
+
+        save.run.relics.push(OwnedRelic {
+            id: "removed".to_string(),
+            counter: 0,
+        });
+        match check_ids(&save, &cards, &EnemiesDB::new(), &RelicDB::new()) {
+            Err(SaveError::UnknownId("relic", id)) => assert_eq!(id, "removed"),
+            other => panic!("expected an unknown relic, got {:?}", other),
+        }
+
+        save.run.deck.push("removed".to_string());
+        assert!(matches!(
+            check_ids(&save, &cards, &EnemiesDB::new(), &RelicDB::new()),
+            Err(SaveError::UnknownId("card", _))
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::components::{Button, Position, Rectangle, SceneOwned, UiAction};
+use crate::tween::DeltaTime;
+use crate::GameScenes;
+
+use raylib::prelude::*;
+use specs::prelude::*;
+use std::collections::HashMap;
+
+// Seconds a faded transition takes to come back from black
+const FADE_TIME: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneChange {
+    // the scene below is paused and keeps its entities
+    Push(GameScenes),
+    Pop,
+    Replace(GameScenes),
+    // drops the whole stack, used when a run ends
+    Reset(GameScenes),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub change: SceneChange,
+    pub fade: bool,
+}
+
+// How far the screen is faded to black, drawn on top by every scene
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScreenFade(pub f32);
+
+pub fn draw_fade(d: &mut impl RaylibDraw, fade: &ScreenFade) {
+    if fade.0 > 0. {
+        let alpha = (fade.0.min(1.) * 255.) as u8;
+        d.draw_rectangle(0, 0, crate::WIDTH, crate::HEIGHT, Color::new(0, 0, 0, alpha));
+    }
+}
+
+pub trait Scene {
+    fn id(&self) -> GameScenes;
+    fn dispatcher(&mut self) -> &mut Dispatcher<'static, 'static>;
+
+    // Called when the scene is pushed, not when the scene above it is popped
+    fn setup(&mut self, _world: &mut World) {}
+
+    fn teardown(&mut self, world: &mut World) {
+        delete_scene_entities(world, self.id());
+    }
+}
+
+pub fn delete_scene_entities(world: &mut World, scene: GameScenes) {
+    {
+        let entities = world.entities();
+        let owned = world.read_storage::<SceneOwned>();
+        for (ent, SceneOwned(owner)) in (&entities, &owned).join() {
+            if *owner == scene {
+                let _res = entities.delete(ent);
+            }
+        }
+    }
+    world.maintain();
+}
+
+pub const BUTTON_WIDTH: f32 = 200.;
+pub const BUTTON_HEIGHT: f32 = 50.;
+
+pub fn spawn_button(world: &mut World, scene: GameScenes, text: &'static str, action: UiAction, position: Position) {
+    world
+        .create_entity()
+        .with(position)
+        .with(Rectangle {
+            width: BUTTON_WIDTH,
+            height: BUTTON_HEIGHT,
+        })
+        .with(Button::new(text, action))
+        .with(SceneOwned(scene))
+        .build();
+}
+
+// A scene that is only its systems plus an optional hook for when it starts,
+// the hook gets the scene's id so shared hooks can tag what they spawn
+pub struct DispatchScene {
+    pub id: GameScenes,
+    pub dispatcher: Dispatcher<'static, 'static>,
+    pub on_setup: Option<fn(&mut World, GameScenes)>,
+}
+
+impl Scene for DispatchScene {
+    fn id(&self) -> GameScenes {
+        self.id
+    }
+
+    fn dispatcher(&mut self) -> &mut Dispatcher<'static, 'static> {
+        &mut self.dispatcher
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        if let Some(on_setup) = self.on_setup {
+            on_setup(world, self.id);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SceneStack {
+    scenes: HashMap<GameScenes, Box<dyn Scene>>,
+    stack: Vec<GameScenes>,
+}
+
+impl SceneStack {
+    pub fn add(&mut self, mut scene: Box<dyn Scene>, world: &mut World) {
+        scene.dispatcher().setup(world);
+        self.scenes.insert(scene.id(), scene);
+    }
+
+    pub fn current(&self) -> Option<GameScenes> {
+        self.stack.last().copied()
+    }
+
+    // Only the top scene runs
+    pub fn dispatch(&mut self, world: &World) {
+        {
+            let delta = world.read_resource::<DeltaTime>();
+            let mut fade = world.write_resource::<ScreenFade>();
+            fade.0 = (fade.0 - delta.0 / FADE_TIME).max(0.);
+        }
+        if let Some(scene) = self.current().and_then(|id| self.scenes.get_mut(&id)) {
+            scene.dispatcher().dispatch(world);
+        }
+    }
+
+    pub fn apply(&mut self, world: &mut World, transition: Transition) {
+        match transition.change {
+            SceneChange::Push(id) => self.push(world, id),
+            SceneChange::Pop => self.pop(world),
+            SceneChange::Replace(id) => {
+                self.pop(world);
+                self.push(world, id);
+            }
+            SceneChange::Reset(id) => {
+                while !self.stack.is_empty() {
+                    self.pop(world);
+                }
+                self.push(world, id);
+            }
+        }
+        if transition.fade {
+            world.write_resource::<ScreenFade>().0 = 1.;
+        }
+        self.resync(world);
+    }
+
+    // Event readers only move on while their scene runs, so the new top scene
+    // would act on events sent while it was hidden, e.g. a game over's Main
+    // Menu click. Setting its systems up again registers fresh readers
+    fn resync(&mut self, world: &mut World) {
+        if let Some(scene) = self.current().and_then(|id| self.scenes.get_mut(&id)) {
+            scene.dispatcher().setup(world);
+        }
+    }
+
+    fn push(&mut self, world: &mut World, id: GameScenes) {
+        // every scene has one dispatcher so it can only be on the stack once
+        debug_assert!(!self.stack.contains(&id), "Scene {:?} is already on the stack", id);
+        if self.stack.contains(&id) {
+            return;
+        }
+        match self.scenes.get_mut(&id) {
+            Some(scene) => {
+                self.stack.push(id);
+                scene.setup(world);
+            }
+            None => println!("Scene {:?} was never added", id),
+        }
+    }
+
+    fn pop(&mut self, world: &mut World) {
+        if let Some(id) = self.stack.pop() {
+            if let Some(scene) = self.scenes.get_mut(&id) {
+                scene.teardown(world);
+            }
+        }
+    }
+}
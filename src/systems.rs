@@ -1,15 +1,28 @@
+use crate::battle::status::Status;
+use crate::battle::action::Action;
+use crate::battle::phase::Phase;
+use crate::battle::{needs_target, Battle, Outcome, Target};
+use crate::map::{NodeKind, NodeRef, RunMap};
+use crate::rng::RunRng;
+use crate::run::Run;
+use crate::scene::{draw_fade, SceneChange, ScreenFade};
 use crate::components::{
-    Active, Button, Card, Enemy, HealthBar, Mousehandler, Player, Position, Rectangle, Sprite,
+    Active, Button, EnemySlot, HandCard, HealthBar, Mousehandler, Player, Position, Rectangle,
+    Rotation, SceneOwned, Sprite, StatusBar, Tint, UiAction,
 };
+use crate::effects::Effect;
+use crate::data_loading::{card_texture_name, enemy_silhouette_name, enemy_texture_name};
 use crate::textures::Textures;
-use crate::{GameScenes, GameState, WIDTH, HEIGHT};
-use crate::cards::generate_intial_deck;
+use crate::tween::{delete_entity, DeltaTime, Easing, Tween, TweenValue, Tweens};
+use crate::{CardDB, CardID, GameScenes, GameState, RelicDB, RelicID, WIDTH, HEIGHT};
 
-use rand::{thread_rng, Rng};
 use raylib::consts::KeyboardKey::*;
 use raylib::prelude::MouseButton::*;
 use raylib::prelude::*;
 use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+use std::collections::HashMap;
+use std::rc::Rc;
 use specs::{Component, VecStorage};
 
 
@@ -18,57 +31,303 @@ use specs::{Component, VecStorage};
 
 pub struct MainMenuDraw {
     pub thread: RaylibThread,
-    pub textures: Textures
+    pub textures: Textures,
+    pub reader: Option<ReaderId<UiAction>>,
 }
 
 impl<'a> System<'a> for MainMenuDraw {
     type SystemData = (
         WriteExpect<'a, RaylibHandle>,
         WriteExpect<'a, GameState>,
+        ReadExpect<'a, RunRng>,
+        ReadExpect<'a, ScreenFade>,
+        Read<'a, EventChannel<UiAction>>,
+        ReadStorage<'a, Button>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Rectangle>,
+        ReadStorage<'a, SceneOwned>,
     );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<EventChannel<UiAction>>().register_reader());
+    }
     
-    fn run(&mut self, (mut rl, mut state): Self::SystemData) {
-        use std::ffi::CString;
-        use raylib::prelude::Rectangle;
-        let width = 200.;
-        let height = 50.;
-        let x = (WIDTH as f32 / 2.) - (width / 2.);
-        let y = (HEIGHT as f32 / 2.) - (height / 2.);
-        
-        let start_btn = rgui::Button {
-            bounds: Rectangle::new(x, y, width, height),
-            text: CString::new("Start Battle").unwrap(),
-        };
-        let how_to_btn = rgui::Button {
-            bounds: Rectangle::new(x, y + 60., width, height),
-            text: CString::new("How to play").unwrap(),
-        };
-    
+    fn run(&mut self, (mut rl, mut state, rng, fade, actions, buttons, positions, rectangles, owners): Self::SystemData) {
         let mut d = rl.begin_drawing(&self.thread);
         d.clear_background(crate::COLOUR);
         let texture = self.textures.get("title");
         let t_width = texture.width;
+        let x = (WIDTH as f32 / 2.) - 100.;
+        let y = (HEIGHT as f32 / 2.) - 25.;
 
         d.draw_texture_ex(texture, Vector2 { x: x - (t_width as f32 / 2.), y: y - 200. }, 0., 2., Color::WHITE);
-        if let rgui::DrawResult::Bool(b) = d.draw_gui(&start_btn) {
-            if b {
-                state.current_scene = GameScenes::Battle;
+        d.draw_text(&format!("seed: {}", rng.seed), 10, HEIGHT - 30, 20, Color::WHITE);
+        draw_buttons(&mut d, GameScenes::MainMenu, (&buttons, &positions, &rectangles, &owners));
+        draw_fade(&mut d, &fade);
+
+        for action in actions.read(self.reader.as_mut().unwrap()) {
+            match action {
+                UiAction::ContinueRun => {
+                    state.continue_run = true;
+                    state.fade(SceneChange::Replace(GameScenes::Map));
+                }
+                UiAction::StartRun | UiAction::HowToPlay => {
+                    state.fade(SceneChange::Replace(GameScenes::Map));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct MapDraw {
+    pub thread: RaylibThread,
+    pub message: String,
+}
+
+fn map_node_position(map: &RunMap, node: NodeRef) -> Vector2 {
+    let width = map.layers[node.layer].len();
+    let spacing = (HEIGHT - 200) as f32 / (map.layers.len() - 1) as f32;
+    Vector2 {
+        x: WIDTH as f32 * (node.index + 1) as f32 / (width + 1) as f32,
+        y: (HEIGHT - 80) as f32 - node.layer as f32 * spacing,
+    }
+}
+
+fn map_node_color(kind: NodeKind) -> Color {
+    match kind {
+        NodeKind::Battle => Color::LIGHTGRAY,
+        NodeKind::Elite => Color::ORANGE,
+        NodeKind::Rest => Color::GREEN,
+        NodeKind::Shop => Color::GOLD,
+        NodeKind::Event => Color::SKYBLUE,
+        NodeKind::Treasure => Color::YELLOW,
+        NodeKind::Boss => Color::RED,
+    }
+}
+
+const MAP_NODE_RADIUS: f32 = 18.;
+
+impl<'a> System<'a> for MapDraw {
+    type SystemData = (
+        WriteExpect<'a, RaylibHandle>,
+        WriteExpect<'a, GameState>,
+        WriteExpect<'a, Run>,
+        WriteExpect<'a, RunRng>,
+        ReadExpect<'a, CardDB>,
+        ReadExpect<'a, RelicDB>,
+        ReadExpect<'a, ScreenFade>,
+    );
+
+    fn run(&mut self, (mut rl, mut state, mut run, mut rng, card_db, relic_db, fade): Self::SystemData) {
+        let mouse_pos = rl.get_mouse_position();
+        let next_nodes = run.next_nodes();
+        let hovered = next_nodes.iter().copied().find(|node| {
+            let center = map_node_position(&run.map, *node);
+            let (dx, dy) = (mouse_pos.x - center.x, mouse_pos.y - center.y);
+            dx * dx + dy * dy <= MAP_NODE_RADIUS * MAP_NODE_RADIUS
+        });
+        if let (Some(node), true) = (hovered, rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON)) {
+            match run.enter(node) {
+                Some(kind) if kind.is_battle() => {
+                    self.message.clear();
+                    state.fade(SceneChange::Push(GameScenes::Battle));
+                }
+                Some(_) => {
+                    self.message = run.resolve_node(&card_db, &relic_db, &mut rng);
+                }
+                None => {}
             }
         }
 
-        if let rgui::DrawResult::Bool(b) = d.draw_gui(&how_to_btn) {
-            if b {
-                state.current_scene = GameScenes::Battle;
+        let mut d = rl.begin_drawing(&self.thread);
+        d.clear_background(crate::COLOUR);
+
+        for (layer, nodes) in run.map.layers.iter().enumerate() {
+            for (index, node) in nodes.iter().enumerate() {
+                let from = map_node_position(&run.map, NodeRef { layer, index });
+                for next in &node.next {
+                    let to = map_node_position(&run.map, NodeRef { layer: layer + 1, index: *next });
+                    d.draw_line_ex(from, to, 2., Color::DARKGRAY);
+                }
+            }
+        }
+
+        for (layer, nodes) in run.map.layers.iter().enumerate() {
+            for (index, node) in nodes.iter().enumerate() {
+                let node_ref = NodeRef { layer, index };
+                let center = map_node_position(&run.map, node_ref);
+                let mut color = map_node_color(node.kind);
+                if !next_nodes.contains(&node_ref) && run.position != Some(node_ref) {
+                    color = Color::new(color.r, color.g, color.b, 90);
+                }
+                let radius = if hovered == Some(node_ref) { MAP_NODE_RADIUS + 4. } else { MAP_NODE_RADIUS };
+                d.draw_circle(center.x as i32, center.y as i32, radius, color);
+                if run.position == Some(node_ref) {
+                    d.draw_circle_lines(center.x as i32, center.y as i32, radius + 4., Color::WHITE);
+                }
+                let label = node.kind.label();
+                let size = measure_text(label, 10);
+                d.draw_text(label, center.x as i32 - (size / 2), center.y as i32 - 5, 10, Color::BLACK);
+            }
+        }
+
+        let hud = format!("HP {}/{}   Gold {}   Deck {}", run.health, run.max_health, run.gold, run.deck.len());
+        d.draw_text(&hud, 10, 10, 20, Color::WHITE);
+        d.draw_text(&self.message, 10, 40, 20, Color::GOLD);
+        let relics = run.relics.iter().map(|owned| (&owned.id, owned.counter));
+        draw_relic_bar(&mut d, &relic_db, relics, &RelicFlashes::default(), Vector2 { x: 10., y: 70. }, mouse_pos);
+        draw_fade(&mut d, &fade);
+    }
+}
+
+pub struct RewardDraw {
+    pub thread: RaylibThread,
+    // shared with `DrawSys`, the card faces are only rendered once
+    pub textures: Rc<Textures>,
+    pub reader: Option<ReaderId<UiAction>>,
+}
+
+impl<'a> System<'a> for RewardDraw {
+    type SystemData = (
+        WriteExpect<'a, RaylibHandle>,
+        WriteExpect<'a, GameState>,
+        WriteExpect<'a, Run>,
+        ReadExpect<'a, ScreenFade>,
+        Read<'a, EventChannel<UiAction>>,
+        ReadStorage<'a, Button>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Rectangle>,
+        ReadStorage<'a, SceneOwned>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<EventChannel<UiAction>>().register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (mut rl, mut state, mut run, fade, actions, buttons, positions, rectangles, owners): Self::SystemData,
+    ) {
+        let mouse_pos: Position = rl.get_mouse_position().into();
+        let clicked = rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON);
+        let count = run.card_rewards.len() as f32;
+        let gap = 40.;
+        let start_x = (WIDTH as f32 - (count * CARD_WIDTH + (count - 1.) * gap)) / 2.;
+        let card_rect = Rectangle {
+            width: CARD_WIDTH,
+            height: CARD_HEIGHT,
+        };
+        let card_positions: Vec<(CardID, Position)> = run
+            .card_rewards
+            .iter()
+            .enumerate()
+            .map(|(index, card_id)| (card_id.clone(), Position::new(start_x + index as f32 * (CARD_WIDTH + gap), 250.)))
+            .collect();
+        let hovered = card_positions
+            .iter()
+            .find(|(_, position)| collision_rect_point(card_rect, *position, mouse_pos))
+            .map(|(card_id, _)| card_id.clone());
+
+        let mut d = rl.begin_drawing(&self.thread);
+        d.clear_background(crate::COLOUR);
+        let title = "Choose a card";
+        d.draw_text(title, (WIDTH / 2) - (measure_text(title, 30) / 2), 150, 30, Color::WHITE);
+        for (card_id, position) in card_positions.iter() {
+            let lift = if hovered.as_ref() == Some(card_id) { -10. } else { 0. };
+            d.draw_texture_ex(
+                self.textures.get(&card_texture_name(card_id)),
+                Vector2 { x: position.x, y: position.y + lift },
+                0.,
+                1.,
+                Color::WHITE,
+            );
+        }
+
+        draw_buttons(&mut d, GameScenes::Reward, (&buttons, &positions, &rectangles, &owners));
+        draw_fade(&mut d, &fade);
+
+        let skipped = actions
+            .read(self.reader.as_mut().unwrap())
+            .any(|action| *action == UiAction::SkipReward);
+        let picked = hovered.filter(|_| clicked);
+        if picked.is_some() || skipped {
+            run.take_reward(picked);
+            // the map is still underneath
+            state.change(SceneChange::Pop);
+        }
+    }
+}
+
+// Shared by the game over and victory scenes
+pub struct RunSummaryDraw {
+    pub thread: RaylibThread,
+    pub scene: GameScenes,
+    pub reader: Option<ReaderId<UiAction>>,
+}
+
+impl<'a> System<'a> for RunSummaryDraw {
+    type SystemData = (
+        WriteExpect<'a, RaylibHandle>,
+        WriteExpect<'a, GameState>,
+        ReadExpect<'a, Run>,
+        ReadExpect<'a, ScreenFade>,
+        Read<'a, EventChannel<UiAction>>,
+        ReadStorage<'a, Button>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Rectangle>,
+        ReadStorage<'a, SceneOwned>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<EventChannel<UiAction>>().register_reader());
+    }
+
+    fn run(
+        &mut self,
+        (mut rl, mut state, run, fade, actions, buttons, positions, rectangles, owners): Self::SystemData,
+    ) {
+        let (title, colour) = if run.is_won() {
+            ("Victory!", Color::GOLD)
+        } else {
+            ("Game Over", Color::MAROON)
+        };
+        let mut lines = vec![
+            format!("Floors cleared: {}", run.stats.floors_cleared),
+            format!("Damage dealt: {}", run.stats.damage_dealt),
+            format!("Cards played: {}", run.stats.cards_played),
+        ];
+        if !run.is_won() {
+            let cause = run.stats.cause_of_death.as_deref().unwrap_or("Unknown");
+            lines.push(format!("Killed by: {}", cause));
+        }
+
+        let mut d = rl.begin_drawing(&self.thread);
+        d.clear_background(crate::COLOUR);
+        d.draw_text(title, (WIDTH / 2) - (measure_text(title, 50) / 2), 150, 50, colour);
+        for (index, line) in lines.iter().enumerate() {
+            let y = 280 + index as i32 * 40;
+            d.draw_text(line, (WIDTH / 2) - (measure_text(line, 25) / 2), y, 25, Color::WHITE);
+        }
+
+        draw_buttons(&mut d, self.scene, (&buttons, &positions, &rectangles, &owners));
+        draw_fade(&mut d, &fade);
+
+        for action in actions.read(self.reader.as_mut().unwrap()) {
+            if *action == UiAction::MainMenu {
+                state.fade(SceneChange::Reset(GameScenes::MainMenu));
             }
         }
-        
     }
 }
 
 // System is not thread safe
 pub struct DrawSys {
     pub thread: RaylibThread,
-    pub textures: Textures,
+    pub textures: Rc<Textures>,
 }
 impl<'a> System<'a> for DrawSys {
     type SystemData = (
@@ -79,48 +338,203 @@ impl<'a> System<'a> for DrawSys {
         ReadStorage<'a, Rectangle>,
         ReadStorage<'a, HealthBar>,
         ReadStorage<'a, Button>,
+        ReadStorage<'a, EnemySlot>,
+        ReadStorage<'a, StatusBar>,
+        ReadStorage<'a, SceneOwned>,
+        ReadStorage<'a, HandCard>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, Tint>,
+        ReadExpect<'a, Battle>,
+        ReadExpect<'a, RelicDB>,
+        Read<'a, RelicFlashes>,
+        ReadExpect<'a, ScreenFade>,
     );
 
-    fn run(&mut self, (mut rl, positions, sprites, active, rectangles, health_bars, buttons): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            mut rl,
+            positions,
+            sprites,
+            active,
+            rectangles,
+            health_bars,
+            buttons,
+            slots,
+            statuses,
+            owners,
+            hand_cards,
+            rotations,
+            tints,
+            battle,
+            relic_db,
+            flashes,
+            fade,
+        ): Self::SystemData,
+    ) {
+        let mouse_pos = rl.get_mouse_position();
         let mut d = rl.begin_drawing(&self.thread);
         d.clear_background(crate::COLOUR);
-        let sprites_to_render: Vec<(&Sprite, &Position, &Active)> = (&sprites, &positions, &active)
-            .join()
-            .filter(|(_, _, active)| active.0)
-            .collect();
-        for (sprite, Position { x, y }, _) in sprites_to_render {
-            d.draw_texture_ex(
-                self.textures.get(&sprite.texture_path),
-                Vector2 { x: *x, y: *y },
-                0.,
-                sprite.scale,
-                Color::WHITE,
+        let mut sprites_to_render: Vec<(&Sprite, &Position, Option<&Rotation>, Option<&Tint>, Option<&HandCard>)> =
+            (&sprites, &positions, &active, rotations.maybe(), tints.maybe(), hand_cards.maybe())
+                .join()
+                .filter(|(_, _, active, _, _, _)| active.0)
+                .map(|(sprite, position, _, rotation, tint, hand_card)| (sprite, position, rotation, tint, hand_card))
+                .collect();
+        // the hand goes over everything else, left to right with the hovered card on top
+        sprites_to_render.sort_by_key(|(_, _, _, _, hand_card)| {
+            hand_card.map(|hand_card| (1, hand_card.hovered, hand_card.index))
+        });
+        for (sprite, Position { x, y }, rotation, tint, _) in sprites_to_render {
+            let texture = self.textures.get(&sprite.texture_path);
+            let width = texture.width as f32 * sprite.scale;
+            let height = texture.height as f32 * sprite.scale;
+            d.draw_texture_pro(
+                texture,
+                raylib::prelude::Rectangle::new(0., 0., texture.width as f32, texture.height as f32),
+                raylib::prelude::Rectangle::new(*x + width / 2., *y + height, width, height),
+                Vector2 { x: width / 2., y: height },
+                rotation.map_or(0., |rotation| rotation.0),
+                tint.map_or(Color::WHITE, |tint| tint.0),
             );
         }
 
         for (health_bar, position, rect) in (&health_bars, &positions, &rectangles).join() {
-            d.draw_rectangle_rec(raylib::prelude::Rectangle::new(position.x, position.y - 50., rect.width * (health_bar.current as f32 / health_bar.max as f32), 40.), Color::GREEN);
+            d.draw_rectangle_rec(raylib::prelude::Rectangle::new(position.x, position.y - 50., rect.width * (health_bar.shown / health_bar.max as f32).max(0.), 40.), Color::GREEN);
             d.draw_rectangle_lines_ex(raylib::prelude::Rectangle::new(position.x, position.y - 50., rect.width, 40.), 3, Color::BLACK);
             let text = format!("{}/{}", health_bar.current, health_bar.max);
             let size = measure_text(&text, 20);
             d.draw_text(&text, (position.x + (rect.width / 2.)) as i32 - (size / 2), position.y as i32 - 40, 20, Color::WHITE);
+            if health_bar.block > 0 {
+                d.draw_rectangle_lines_ex(raylib::prelude::Rectangle::new(position.x, position.y - 50., rect.width, 40.), 3, Color::SKYBLUE);
+                let shield_x = position.x as i32 - 15;
+                let shield_y = position.y as i32 - 30;
+                d.draw_circle(shield_x, shield_y, 15., Color::BLUE);
+                let text = health_bar.block.to_string();
+                let size = measure_text(&text, 20);
+                d.draw_text(&text, shield_x - (size / 2), shield_y - 10, 20, Color::WHITE);
+            }
         }
 
-        for (Button { color, text, hover, ..}, position, rect) in (&buttons, &positions, &rectangles).join() {
-            let alpha = if *hover {
-                100
-            } else {
-                255
-            };
-            let color = Color::new(color[0], color[1], color[2], alpha);
-            d.draw_rectangle_rec(raylib::prelude::Rectangle::new(position.x, position.y, rect.width, rect.height), color);
-            d.draw_rectangle_lines_ex(raylib::prelude::Rectangle::new(position.x, position.y, rect.width, rect.height), 3, Color::BLACK);
-            let size = measure_text(&text, 20);
-            d.draw_text(&text, (position.x + (rect.width / 2.)) as i32 - (size / 2), position.y as i32 + 15, 20, Color::WHITE);
+        for (StatusBar(statuses), position, rect) in (&statuses, &positions, &rectangles).join() {
+            for (index, (status, stacks)) in statuses.iter().enumerate() {
+                let x = (position.x + rect.width) as i32 + 5 + (index as i32 * 30);
+                let y = position.y as i32 - 50;
+                d.draw_rectangle(x, y, 26, 26, status_color(status));
+                d.draw_text(status.short_name(), x + 3, y + 3, 10, Color::WHITE);
+                d.draw_text(&stacks.to_string(), x + 12, y + 14, 10, Color::WHITE);
+            }
+        }
+
+        draw_buttons(&mut d, GameScenes::Battle, (&buttons, &positions, &rectangles, &owners));
+
+        for (EnemySlot(index), position, rect) in (&slots, &positions, &rectangles).join() {
+            let enemy = &battle.enemies[*index];
+            if let Some(intent) = battle.intent(*index) {
+                let text = if enemy.intent_known() { intent.describe() } else { "?".to_string() };
+                let size = measure_text(&text, 20);
+                d.draw_text(&text, (position.x + (rect.width / 2.)) as i32 - (size / 2), position.y as i32 - 75, 20, Color::ORANGE);
+            }
+        }
+
+        let energy_x = WIDTH - 125;
+        let energy_y = HEIGHT - 160;
+        d.draw_circle(energy_x, energy_y, 35., Color::GOLD);
+        d.draw_circle_lines(energy_x, energy_y, 35., Color::BLACK);
+        let text = format!("{}/{}", battle.energy, battle.max_energy);
+        let size = measure_text(&text, 20);
+        d.draw_text(&text, energy_x - (size / 2), energy_y - 10, 20, Color::BLACK);
+
+        if battle.phase != Phase::PlayerMain {
+            let text = battle.phase.name();
+            let size = measure_text(text, 30);
+            d.draw_text(text, (WIDTH / 2) - (size / 2), 20, 30, Color::WHITE);
+        }
+        draw_relic_bar(&mut d, &relic_db, battle.relic_counters(), &flashes, Vector2 { x: 10., y: 10. }, mouse_pos);
+        draw_fade(&mut d, &fade);
+    }
+}
+
+const RELIC_ICON_RADIUS: f32 = 14.;
+const RELIC_FLASH_TIME: f32 = 0.6;
+
+// Seconds left on the flash of each relic that just fired
+#[derive(Debug, Default)]
+pub struct RelicFlashes(pub HashMap<RelicID, f32>);
+
+pub struct RelicFlashSys;
+
+impl<'a> System<'a> for RelicFlashSys {
+    type SystemData = (Read<'a, DeltaTime>, WriteExpect<'a, Battle>, Write<'a, RelicFlashes>);
+
+    fn run(&mut self, (delta, mut battle, mut flashes): Self::SystemData) {
+        for time in flashes.0.values_mut() {
+            *time -= delta.0;
+        }
+        flashes.0.retain(|_, time| *time > 0.);
+        for id in battle.flashes.drain(..) {
+            flashes.0.insert(id, RELIC_FLASH_TIME);
         }
     }
 }
 
+// A row of relic icons with their counters, hovering one shows what it does
+fn draw_relic_bar<'r>(
+    d: &mut impl RaylibDraw,
+    relic_db: &RelicDB,
+    relics: impl Iterator<Item = (&'r RelicID, u32)>,
+    flashes: &RelicFlashes,
+    origin: Vector2,
+    mouse_pos: Vector2,
+) {
+    let mut tooltip = None;
+    for (index, (id, counter)) in relics.enumerate() {
+        let relic = match relic_db.get(id) {
+            Some(relic) => relic,
+            None => continue,
+        };
+        let x = origin.x + RELIC_ICON_RADIUS + index as f32 * (RELIC_ICON_RADIUS * 2. + 8.);
+        let y = origin.y + RELIC_ICON_RADIUS;
+        let (r, g, b) = relic.color;
+        d.draw_circle(x as i32, y as i32, RELIC_ICON_RADIUS, Color::new(r, g, b, 255));
+        let letter: String = relic.name.chars().take(1).collect();
+        let size = measure_text(&letter, 20);
+        d.draw_text(&letter, x as i32 - (size / 2), y as i32 - 9, 20, Color::BLACK);
+        if relic.every > 1 {
+            d.draw_text(&counter.to_string(), (x + RELIC_ICON_RADIUS / 2.) as i32, (y + RELIC_ICON_RADIUS / 2.) as i32, 10, Color::WHITE);
+        }
+        if let Some(time) = flashes.0.get(id) {
+            let t = time / RELIC_FLASH_TIME;
+            let alpha = (t * 255.) as u8;
+            d.draw_circle_lines(x as i32, y as i32, RELIC_ICON_RADIUS + (1. - t) * 10., Color::new(255, 255, 255, alpha));
+        }
+        let (dx, dy) = (mouse_pos.x - x, mouse_pos.y - y);
+        if dx * dx + dy * dy <= RELIC_ICON_RADIUS * RELIC_ICON_RADIUS {
+            tooltip = Some((relic, y + RELIC_ICON_RADIUS + 6.));
+        }
+    }
+    if let Some((relic, y)) = tooltip {
+        let text = format!("{}\n{}", relic.name, relic.description());
+        let width = text.lines().map(|line| measure_text(line, 10)).max().unwrap_or(0);
+        let height = text.lines().count() as i32 * 12;
+        let (x, y) = (mouse_pos.x as i32, y as i32);
+        d.draw_rectangle(x - 4, y - 4, width + 8, height + 8, Color::new(0, 0, 0, 200));
+        for (index, line) in text.lines().enumerate() {
+            d.draw_text(line, x, y + index as i32 * 12, 10, Color::WHITE);
+        }
+    }
+}
+
+fn status_color(status: Status) -> Color {
+    match status {
+        Status::Poison => Color::GREEN,
+        Status::Weak => Color::GRAY,
+        Status::Vulnerable => Color::MAROON,
+        Status::Strength => Color::RED,
+        Status::Barricade | Status::Blur => Color::BLUE,
+    }
+}
+
 pub fn collision_rect_point(rectangle: Rectangle, position: Position, point: Position) -> bool {
     let Rectangle { width, height } = rectangle;
     position.x <= point.x
@@ -129,123 +543,411 @@ pub fn collision_rect_point(rectangle: Rectangle, position: Position, point: Pos
         && point.y <= position.y + height
 }
 
-pub struct ButtonHandler;
+pub fn draw_buttons(
+    d: &mut impl RaylibDraw,
+    scene: GameScenes,
+    (buttons, positions, rectangles, owners): (
+        &ReadStorage<Button>,
+        &ReadStorage<Position>,
+        &ReadStorage<Rectangle>,
+        &ReadStorage<SceneOwned>,
+    ),
+) {
+    for (Button { color, text, hover, ..}, position, rect, SceneOwned(owner)) in (buttons, positions, rectangles, owners).join() {
+        // paused scenes lower on the stack keep their buttons but don't show them
+        if *owner != scene {
+            continue;
+        }
+        let alpha = if *hover {
+            100
+        } else {
+            255
+        };
+        let color = Color::new(color[0], color[1], color[2], alpha);
+        d.draw_rectangle_rec(raylib::prelude::Rectangle::new(position.x, position.y, rect.width, rect.height), color);
+        d.draw_rectangle_lines_ex(raylib::prelude::Rectangle::new(position.x, position.y, rect.width, rect.height), 3, Color::BLACK);
+        let size = measure_text(text, 20);
+        d.draw_text(text, (position.x + (rect.width / 2.)) as i32 - (size / 2), position.y as i32 + 15, 20, Color::WHITE);
+    }
+}
+
+// Turns clicks on the current scene's buttons into `UiAction` events
+pub struct ButtonHandler {
+    pub scene: GameScenes,
+}
+
 impl<'a> System<'a> for ButtonHandler {
     type SystemData = (
         ReadExpect<'a, RaylibHandle>,
-        WriteExpect<'a, BattleState>,
+        Write<'a, EventChannel<UiAction>>,
         WriteStorage<'a, Button>,
         ReadStorage<'a, Rectangle>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, SceneOwned>,
     );
 
-    fn run(&mut self, (rl, mut battle_state, mut buttons, rectangles, positions): Self::SystemData) {
+    fn run(&mut self, (rl, mut actions, mut buttons, rectangles, positions, owners): Self::SystemData) {
         let mouse_pos = rl.get_mouse_position();
-        for (button, rect, position) in (&mut buttons, &rectangles, &positions).join() {
+        let clicked = rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON);
+        for (button, rect, position, SceneOwned(owner)) in (&mut buttons, &rectangles, &positions, &owners).join() {
+            if *owner != self.scene {
+                continue;
+            }
             button.hover = collision_rect_point(*rect, *position, mouse_pos.into());
-            if button.hover && rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON) {
-                match button.action {
-                    "end_turn" => {
-                        battle_state.end_of_turn = true;
-                    },
-                    _ => {}
-                }
+            if button.hover && clicked {
+                actions.single_write(button.action);
             }
         }
     }
 }
-use crate::CardID;
-#[derive(Debug, Default)]
-pub struct BattleState {
-    card_queue: Vec<Card>,
-    targets: Vec<Entity>,
-    end_of_turn: bool,
-    deck: Vec<CardID>,
-    hand: Vec<CardID>,
-    discard: Vec<CardID>
+
+#[derive(Default)]
+pub struct BattleActions {
+    pub reader: Option<ReaderId<UiAction>>,
 }
 
-impl BattleState {
-    pub fn new(cards: &crate::CardDB) -> Self {
-        BattleState {
-            card_queue: Vec::new(),
-            targets: Vec::new(),
-            end_of_turn: false,
-            deck: generate_intial_deck(cards),
-            hand: Vec::new(),
-            discard: Vec::new()
+impl<'a> System<'a> for BattleActions {
+    type SystemData = (
+        Read<'a, EventChannel<UiAction>>,
+        WriteExpect<'a, Battle>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<EventChannel<UiAction>>().register_reader());
+    }
+
+    fn run(&mut self, (actions, mut battle): Self::SystemData) {
+        for action in actions.read(self.reader.as_mut().unwrap()) {
+            if *action == UiAction::EndTurn {
+                battle.end_turn();
+            }
         }
     }
-    
 }
 
-pub struct EnemyAttacker;
-impl<'a> System<'a> for EnemyAttacker {
+// Seconds until the next queued battle action may resolve
+#[derive(Debug, Default)]
+pub struct ActionTimer(pub f32);
+
+// How long each action holds up the queue so its animation can be seen
+fn action_delay(action: &Action) -> f32 {
+    match action {
+        Action::Effect(Effect::DealDamage(_), _) => 0.3,
+        Action::Effect(_, _) => 0.15,
+        Action::EnterPhase(Phase::PlayerTurnStart) | Action::EnterPhase(Phase::PlayerTurnEnd) => 0.3,
+        Action::EnterPhase(_) => 0.,
+        Action::EnemyTurnStart(_) | Action::EnemyTurnEnd(_) | Action::CardResolved => 0.,
+        Action::EnemyMove(_) | Action::EnemyHit(_, _) => 0.45,
+    }
+}
+
+// Works through the battle's action queue one action at a time
+pub struct ResolveActions;
+
+impl<'a> System<'a> for ResolveActions {
     type SystemData = (
-        WriteStorage<'a, Enemy>,
-        WriteExpect<'a, BattleState>,
-        ReadStorage<'a, Player>,
-        WriteStorage<'a, HealthBar>,
+        Read<'a, DeltaTime>,
+        Write<'a, ActionTimer>,
+        WriteExpect<'a, Battle>,
+        WriteExpect<'a, RunRng>,
     );
 
-    fn run(&mut self, (enemies, mut battle_state, players, mut health_bars): Self::SystemData) {
-        if battle_state.end_of_turn {
-            for enemy in enemies.join() {
-                if enemy.open {
-                    for (_, health_bar) in (&players, &mut health_bars).join() {
-                        health_bar.current = std::cmp::max(0, health_bar.current - enemy.attack);
-                    }
+    fn run(&mut self, (delta, mut timer, mut battle, mut rng): Self::SystemData) {
+        timer.0 -= delta.0;
+        while timer.0 <= 0. {
+            match battle.step(&mut rng) {
+                Some(action) => timer.0 += action_delay(&action),
+                None => {
+                    timer.0 = 0.;
+                    break;
                 }
             }
-            battle_state.end_of_turn = false;
         }
     }
 }
 
-pub struct DamageQueue;
-impl<'a> System<'a> for DamageQueue {
+const CARD_WIDTH: f32 = 120.;
+const CARD_HEIGHT: f32 = 160.;
+const HAND_Y: f32 = HEIGHT as f32 - CARD_HEIGHT - 20.;
+const MAX_HAND_WIDTH: f32 = 560.;
+// total spread of the fan in degrees
+const MAX_FAN_ANGLE: f32 = 30.;
+const HOVER_LIFT: f32 = 40.;
+// cards that don't need an enemy are played by dropping them above the hand
+const PLAY_ZONE_BOTTOM: f32 = HAND_Y - HOVER_LIFT;
+const HAND_EASE_SPEED: f32 = 14.;
+// new cards fly in from here
+const DRAW_PILE_POSITION: Position = Position { x: -CARD_WIDTH, y: HEIGHT as f32 };
+// and cards leaving the hand fly off to here
+const DISCARD_PILE_POSITION: Position = Position { x: WIDTH as f32, y: HEIGHT as f32 };
+const HEALTH_TWEEN_TIME: f32 = 0.4;
+const DEATH_TWEEN_TIME: f32 = 0.6;
+const DISCARD_TWEEN_TIME: f32 = 0.35;
+// played cards pop to this size and shrink to half on the way to the pile
+const DISCARD_POP_SCALE: f32 = 1.3;
+const DISCARD_SPIN: f32 = 180.;
+// dead enemies tip over backwards as they fade
+const DEATH_TILT: f32 = -90.;
+
+fn tween_health(tweens: &mut WriteStorage<Tweens>, ent: Entity, health_bar: &HealthBar) {
+    let tween = Tween::new(
+        TweenValue::HealthFill(health_bar.shown, health_bar.current as f32),
+        HEALTH_TWEEN_TIME,
+        Easing::EaseOutCubic,
+    );
+    Tweens::start(tweens, ent, tween);
+}
+
+fn fade_out(tweens: &mut WriteStorage<Tweens>, ent: Entity, duration: f32) {
+    let tween = Tween::new(TweenValue::Tint(Color::WHITE, Color::new(255, 255, 255, 0)), duration, Easing::EaseInQuad)
+        .then(delete_entity);
+    Tweens::start(tweens, ent, tween);
+}
+
+// Mirrors the battle onto its entities, the battle itself never touches specs
+pub struct BattleSync {
+    pub hand: Vec<CardID>,
+}
+
+impl<'a> System<'a> for BattleSync {
     type SystemData = (
-        WriteExpect<'a, BattleState>,
-        WriteStorage<'a, Enemy>,
+        ReadExpect<'a, Battle>,
+        WriteStorage<'a, EnemySlot>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, HandCard>,
         WriteStorage<'a, HealthBar>,
+        WriteStorage<'a, StatusBar>,
+        WriteStorage<'a, Sprite>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Rectangle>,
+        WriteStorage<'a, Active>,
+        WriteStorage<'a, Rotation>,
+        WriteStorage<'a, SceneOwned>,
+        WriteStorage<'a, Tweens>,
         Entities<'a>,
     );
 
     fn run(
         &mut self,
-        (mut battle_state, mut enemies, mut health_bars, entities): Self::SystemData,
+        (
+            battle,
+            mut slots,
+            players,
+            mut hand_cards,
+            mut health_bars,
+            mut statuses,
+            mut sprites,
+            mut positions,
+            mut rectangles,
+            mut actives,
+            mut rotations,
+            mut owners,
+            mut tweens,
+            entities,
+        ): Self::SystemData,
     ) {
-        for i in 0..battle_state.card_queue.len() {
-            let card = battle_state.card_queue.get(i).unwrap().clone();
-            let target_ent = battle_state.targets.get_mut(i).unwrap();
-            let enemy = enemies.get_mut(*target_ent).unwrap();
-            let health_bar = health_bars.get_mut(*target_ent).unwrap();
-            enemy.health = std::cmp::max(0, enemy.health - card.value);
-            if enemy.health == 0 {
-                entities.delete(*target_ent);
+        for (_, health_bar, ent) in (&players, &mut health_bars, &entities).join() {
+            health_bar.max = battle.player.max_health;
+            health_bar.block = battle.player.block;
+            if health_bar.current != battle.player.health {
+                health_bar.current = battle.player.health;
+                tween_health(&mut tweens, ent, health_bar);
             }
-            health_bar.current = enemy.health;
+            let _res = statuses.insert(ent, StatusBar(battle.player.statuses.clone()));
+        }
+
+        let mut dying = Vec::new();
+        for (EnemySlot(index), sprite, ent) in (&slots, &mut sprites, &entities).join() {
+            let enemy = &battle.enemies[*index];
+            if enemy.fighter.is_dead() {
+                dying.push(ent);
+                continue;
+            }
+            if enemy.open {
+                sprite.texture_path = enemy_texture_name(&enemy.id);
+                match health_bars.get_mut(ent) {
+                    Some(health_bar) => {
+                        health_bar.max = enemy.fighter.max_health;
+                        health_bar.block = enemy.fighter.block;
+                        if health_bar.current != enemy.fighter.health {
+                            health_bar.current = enemy.fighter.health;
+                            tween_health(&mut tweens, ent, health_bar);
+                        }
+                    }
+                    None => {
+                        let _res = health_bars.insert(ent, HealthBar::new(&enemy.fighter));
+                    }
+                }
+                let _res = statuses.insert(ent, StatusBar(enemy.fighter.statuses.clone()));
+            } else {
+                sprite.texture_path = enemy_silhouette_name(&enemy.id);
+            }
+        }
+        // the dead stop being targets straight away but fade out before going
+        for ent in dying {
+            slots.remove(ent);
+            health_bars.remove(ent);
+            statuses.remove(ent);
+            let scale = sprites.get(ent).map_or(1., |sprite| sprite.scale);
+            let shrink = Tween::new(TweenValue::Scale(scale, 0.), DEATH_TWEEN_TIME, Easing::Linear);
+            Tweens::start(&mut tweens, ent, shrink);
+            let tilt = Tween::new(TweenValue::Rotation(0., DEATH_TILT), DEATH_TWEEN_TIME, Easing::EaseInOutQuad);
+            Tweens::start(&mut tweens, ent, tilt);
+            fade_out(&mut tweens, ent, DEATH_TWEEN_TIME);
+        }
+
+        if self.hand != battle.deck.hand || hand_cards.count() != battle.deck.hand.len() {
+            // keep the entities of cards that are still in hand so they slide
+            // to their new place instead of popping in again
+            let mut existing: Vec<(usize, Entity, CardID)> = (&entities, &hand_cards)
+                .join()
+                .map(|(ent, hand_card)| (hand_card.index, ent, hand_card.card_id.clone()))
+                .collect();
+            existing.sort_by_key(|(index, _, _)| *index);
+            let kept: Vec<Option<Entity>> = battle
+                .deck
+                .hand
+                .iter()
+                .map(|card_id| {
+                    let found = existing.iter().position(|(_, _, id)| id == card_id)?;
+                    Some(existing.remove(found).1)
+                })
+                .collect();
+            // played and discarded cards fly off to the discard pile
+            for (_, ent, _) in existing {
+                hand_cards.remove(ent);
+                let from = positions.get(ent).copied().unwrap_or(DRAW_PILE_POSITION);
+                let tween = Tween::new(TweenValue::Position(from, DISCARD_PILE_POSITION), DISCARD_TWEEN_TIME, Easing::EaseInQuad);
+                Tweens::start(&mut tweens, ent, tween);
+                let scale = sprites.get(ent).map_or(1., |sprite| sprite.scale);
+                let pop = Tween::new(TweenValue::Scale(scale * DISCARD_POP_SCALE, scale * 0.5), DISCARD_TWEEN_TIME, Easing::EaseOutBack);
+                Tweens::start(&mut tweens, ent, pop);
+                let angle = rotations.get(ent).map_or(0., |rotation| rotation.0);
+                let spin = Tween::new(TweenValue::Rotation(angle, angle + DISCARD_SPIN), DISCARD_TWEEN_TIME, Easing::EaseOutQuad);
+                Tweens::start(&mut tweens, ent, spin);
+                fade_out(&mut tweens, ent, DISCARD_TWEEN_TIME);
+            }
+            for (index, (card_id, ent)) in battle.deck.hand.iter().zip(kept).enumerate() {
+                if let Some(hand_card) = ent.and_then(|ent| hand_cards.get_mut(ent)) {
+                    hand_card.index = index;
+                    continue;
+                }
+                entities
+                    .build_entity()
+                    .with(
+                        HandCard {
+                            card_id: card_id.clone(),
+                            index,
+                            hovered: false,
+                        },
+                        &mut hand_cards,
+                    )
+                    .with(DRAW_PILE_POSITION, &mut positions)
+                    .with(Rotation(0.), &mut rotations)
+                    .with(
+                        Rectangle {
+                            width: CARD_WIDTH,
+                            height: CARD_HEIGHT,
+                        },
+                        &mut rectangles,
+                    )
+                    .with(
+                        Sprite {
+                            texture_path: card_texture_name(card_id),
+                            scale: 1.,
+                        },
+                        &mut sprites,
+                    )
+                    .with(Active(true), &mut actives)
+                    .with(SceneOwned(GameScenes::Battle), &mut owners)
+                    .build();
+            }
+            self.hand = battle.deck.hand.clone();
+        }
+    }
+}
+
+// Fans the hand out in an arc and eases every card towards its place, so
+// drawing, playing and hovering all animate
+pub struct HandLayout;
+
+impl<'a> System<'a> for HandLayout {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, HandCard>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Rotation>,
+    );
+
+    fn run(&mut self, (delta, hand_cards, mut positions, mut rotations): Self::SystemData) {
+        let count = hand_cards.count();
+        if count == 0 {
+            return;
+        }
+        let spacing = (MAX_HAND_WIDTH / count as f32).min(CARD_WIDTH * 0.8);
+        let angle_step = (MAX_FAN_ANGLE / count as f32).min(6.);
+        // frame rate independent easing
+        let t = 1. - (-HAND_EASE_SPEED * delta.0).exp();
+        for (hand_card, position, rotation) in (&hand_cards, &mut positions, &mut rotations).join() {
+            let offset = hand_card.index as f32 - (count as f32 - 1.) / 2.;
+            let mut target = Position::new(
+                WIDTH as f32 / 2. + offset * spacing - CARD_WIDTH / 2.,
+                HAND_Y + offset * offset * 3.,
+            );
+            let mut angle = offset * angle_step;
+            if hand_card.hovered {
+                target.y = HAND_Y - HOVER_LIFT;
+                angle = 0.;
+            }
+            position.x += (target.x - position.x) * t;
+            position.y += (target.y - position.y) * t;
+            rotation.0 += (angle - rotation.0) * t;
+        }
+    }
+}
+
+pub struct BattleEnd;
+impl<'a> System<'a> for BattleEnd {
+    type SystemData = (
+        ReadExpect<'a, Battle>,
+        Read<'a, ActionTimer>,
+        WriteExpect<'a, Run>,
+        WriteExpect<'a, RunRng>,
+        ReadExpect<'a, CardDB>,
+        WriteExpect<'a, GameState>,
+    );
+
+    fn run(&mut self, (battle, timer, mut run, mut rng, card_db, mut state): Self::SystemData) {
+        // let the killing blow play out first
+        if battle.phase != Phase::BattleEnd || timer.0 > 0. {
+            return;
+        }
+        if let Some(outcome) = battle.outcome() {
+            run.finish_battle(&battle, &card_db, &mut rng);
+            let next = match outcome {
+                Outcome::Won if run.is_won() => GameScenes::Victory,
+                Outcome::Won => GameScenes::Reward,
+                Outcome::Lost => GameScenes::GameOver,
+            };
+            state.fade(SceneChange::Replace(next));
         }
-        battle_state.card_queue = vec![];
-        battle_state.targets = vec![];
     }
 }
 
 pub struct CardSelector {
-    pub selected: Option<Entity>,
     pub dragging: Option<Entity>,
 }
 
 impl<'a> System<'a> for CardSelector {
     type SystemData = (
-        WriteExpect<'a, BattleState>,
+        WriteExpect<'a, Battle>,
+        ReadExpect<'a, CardDB>,
         ReadStorage<'a, Mousehandler>,
         ReadExpect<'a, RaylibHandle>,
-        ReadStorage<'a, Card>,
+        WriteStorage<'a, HandCard>,
         WriteStorage<'a, Rectangle>,
-        WriteStorage<'a, Sprite>,
-        WriteStorage<'a, Enemy>,
-        WriteStorage<'a, HealthBar>,
+        ReadStorage<'a, EnemySlot>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, Active>,
         Entities<'a>,
@@ -254,115 +956,82 @@ impl<'a> System<'a> for CardSelector {
     fn run(
         &mut self,
         (
-            mut battle_state,
+            mut battle,
+            card_db,
             mouse_handler,
             rl,
-            cards,
+            mut hand_cards,
             mut rectangles,
-            mut sprites,
-            mut enemies,
-            mut health_bars,
+            slots,
             mut positions,
             mut actives,
             entities,
         ): Self::SystemData,
     ) {
-        let mut selected: Option<Entity> = None;
+        let mouse_pos: Position = rl.get_mouse_position().into();
 
-        for (rect, position, ent, _) in (&mut rectangles, &mut positions, &entities, &cards).join() {
-            if collision_rect_point(*rect, *position, rl.get_mouse_position().into()) {
-                selected = Option::from(ent);
+        if self.dragging.is_none() {
+            // cards overlap, the one drawn last is on top. A lifted card still
+            // counts the space it rose out of so it doesn't flicker
+            let hovered = (&entities, &hand_cards, &rectangles, &positions)
+                .join()
+                .filter(|(_, hand_card, rect, position)| {
+                    let lift = if hand_card.hovered { HOVER_LIFT } else { 0. };
+                    let rect = Rectangle {
+                        width: rect.width,
+                        height: rect.height + lift,
+                    };
+                    collision_rect_point(rect, **position, mouse_pos)
+                })
+                .max_by_key(|(_, hand_card, _, _)| hand_card.index)
+                .map(|(ent, _, _, _)| ent);
+            for (ent, hand_card) in (&entities, &mut hand_cards).join() {
+                hand_card.hovered = Some(ent) == hovered;
             }
-        }
 
-        if let Some(s) = selected {
-            if selected != self.selected {
-                if let Some(gs) = self.selected {
-                    positions.get_mut(gs).unwrap().y += 50.;
+            // cards can only be picked up on the player's turn
+            let can_act = battle.phase == Phase::PlayerMain;
+            if let (Some(ent), true) = (hovered, can_act && rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON)) {
+                self.dragging = Some(ent);
+                if let Some((mouse, _)) = (&entities, &mouse_handler).join().next() {
+                    let _res = actives.insert(ent, Active(false));
+                    let _res = actives.insert(mouse, Active(true));
                 }
-                positions.get_mut(s).unwrap().y -= 50.;
-                self.selected = Option::from(s);
-            }
-        } else {
-            if let Some(gs) = self.selected {
-                positions.get_mut(gs).unwrap().y += 50.;
             }
-            self.selected = None;
-        }
-
-        if rl.is_mouse_button_pressed(MOUSE_LEFT_BUTTON) && self.selected.is_some() {
-            self.dragging = self.selected;
-            let mouse_ent = (&*entities, &mouse_handler).join().next().unwrap();
-            actives.get_mut(self.dragging.unwrap()).unwrap().0 = false;
-            actives.get_mut(mouse_ent.0).unwrap().0 = true;
         }
 
         if rl.is_mouse_button_down(MOUSE_LEFT_BUTTON) {
             let mouse_ent = (&*entities, &mouse_handler).join().next();
             if let Some(mouse) = mouse_ent {
                 let rectangle = rectangles.get_mut(mouse.0).unwrap();
-                let mouse_pos: Position = rl.get_mouse_position().into();
                 positions.get_mut(mouse.0).unwrap().x = mouse_pos.x - (rectangle.width / 2.);
                 positions.get_mut(mouse.0).unwrap().y = mouse_pos.y - (rectangle.height / 2.);
             }
-           
-        }
-
-        if rl.is_mouse_button_up(MOUSE_LEFT_BUTTON) && self.dragging.is_some() {
-            let mouse_ent = (&*entities, &mouse_handler).join().next().unwrap();
-            actives.get_mut(mouse_ent.0).unwrap().0 = false;
-            let mut removed = false;
-            for (rectangle, position, enemy, ent) in (&mut rectangles, &mut positions, &mut enemies, &*entities).join() {
-                if collision_rect_point(*rectangle, *position, rl.get_mouse_position().into()) {
-                    if !enemy.open {
-                        let sprite = sprites.get_mut(ent).unwrap();
-                        let template_name = format!("h:{0}_a:{1}", enemy.health, enemy.attack);
-                        sprite.texture_path = template_name;
-                        enemy.open = true;
-                        health_bars.insert(ent, HealthBar::new(enemy.health));
-                    }
-                    let _res = entities.delete(self.dragging.unwrap());
-                    removed = true;
-                    battle_state
-                        .card_queue
-                        .push(cards.get(self.dragging.unwrap()).unwrap().clone());
-                    battle_state.targets.push(ent);
-                    break;
-                }
-            }
-            if !removed {
-                actives.get_mut(self.dragging.unwrap()).unwrap().0 = true;
-            }
-            self.dragging = None;
         }
-    }
-}
-
 
-pub struct DrawCardSys;
-
-impl<'a> System<'a> for DrawCardSys {
-    type SystemData = (
-        WriteExpect<'a, BattleState>,
-    );
-    fn run(&mut self, (mut battle_state,):Self::SystemData) {
-        if battle_state.hand.is_empty() {
-            if battle_state.deck.len() <= 2 {
-                let mut discard_pile = battle_state.discard.clone();
-                //@TODO:
-                // shuffle discard into deck
-                // for now: 
-                battle_state.deck.append(&mut discard_pile);
-                battle_state.discard = Vec::new();
+        if let (Some(dragging), true) = (self.dragging, rl.is_mouse_button_up(MOUSE_LEFT_BUTTON)) {
+            if let Some((mouse, _)) = (&entities, &mouse_handler).join().next() {
+                let _res = actives.insert(mouse, Active(false));
             }
-
-            for _ in 0..2 {
-                if let Some(card_id) = battle_state.deck.pop() {
-                    battle_state.hand.push(card_id);
+            let _res = actives.insert(dragging, Active(true));
+            // a played card leaves `Deck::hand` and `BattleSync` removes its entity
+            let card = hand_cards.get(dragging).and_then(|hand_card| card_db.get(&hand_card.card_id));
+            if let Some(card) = card {
+                let target = if needs_target(&card.effects) {
+                    (&rectangles, &positions, &slots)
+                        .join()
+                        .find(|(rectangle, position, _)| collision_rect_point(**rectangle, **position, mouse_pos))
+                        .map(|(_, _, EnemySlot(index))| Target::Enemy(*index))
+                } else if mouse_pos.y < PLAY_ZONE_BOTTOM {
+                    Some(Target::None)
                 } else {
-                    panic!("You tried to take a card from the deck without there being a value in the deck");
+                    None
+                };
+                if let Some(target) = target {
+                    let _res = battle.play_card(&card_db, &card.id, target);
                 }
             }
+            self.dragging = None;
         }
     }
-}
\ No newline at end of file
+}
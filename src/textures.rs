@@ -1,21 +1,44 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use raylib::core::{ RaylibHandle, RaylibThread };
 use raylib::core::texture::Texture2D;
 use raylib::prelude::*;
 
+// Magenta and black so a missing asset is obvious without crashing the game
+pub fn missing_image(width: i32, height: i32) -> Image {
+    Image::gen_image_checked(width, height, 8, 8, Color::MAGENTA, Color::BLACK)
+}
+
+pub fn load_image(location: &str) -> Image {
+    match Image::load_image(location) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("Failed to load image {}: {}", location, e);
+            missing_image(64, 64)
+        }
+    }
+}
 
 pub fn load_texture(
     location: &str,
     rl: &mut RaylibHandle,
     thread: &RaylibThread,
-) -> Texture2D {
-    let i = Image::load_image(location).expect("could not load image");
-    rl.load_texture_from_image(&thread, &i)
-        .expect("could not load texture from image")
+) -> Option<Texture2D> {
+    let i = load_image(location);
+    match rl.load_texture_from_image(&thread, &i) {
+        Ok(texture) => Some(texture),
+        Err(e) => {
+            println!("Failed to create texture from {}: {}", location, e);
+            None
+        }
+    }
 }
 
 pub struct Textures {
-    pub data: HashMap<String, raylib::core::texture::Texture2D>
+    pub data: HashMap<String, raylib::core::texture::Texture2D>,
+    missing: Texture2D,
+    // only complain once about each missing key, `get` runs every frame
+    warned: RefCell<HashSet<String>>,
 }
 
 
@@ -23,14 +46,29 @@ impl Textures {
     pub fn from_paths(to_load: Vec<&str>, mut rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
         let mut data: HashMap<String, Texture2D> = HashMap::new();
         for l in to_load {
-            data.insert(l.to_string(), load_texture(&format!("assets/{}.png", l).to_string(), &mut rl, &thread));
+            if let Some(texture) = load_texture(&format!("assets/{}.png", l), &mut rl, &thread) {
+                data.insert(l.to_string(), texture);
+            }
         }
+        let missing = rl
+            .load_texture_from_image(&thread, &missing_image(64, 64))
+            .expect("could not create the missing texture");
         Textures {
-            data
+            data,
+            missing,
+            warned: RefCell::new(HashSet::new()),
         }
     }
     pub fn get(&self, texture: &str) -> &Texture2D {
-        self.data.get(texture).expect("Couldn't load texture")
+        match self.data.get(texture) {
+            Some(t) => t,
+            None => {
+                if self.warned.borrow_mut().insert(texture.to_string()) {
+                    println!("Missing texture {}", texture);
+                }
+                &self.missing
+            }
+        }
     }
     pub fn add(&mut self, name: &str, texture: Texture2D) {
         self.data.insert(name.to_string(), texture);
@@ -38,4 +76,4 @@ impl Textures {
     pub fn contains(&self, name: &str) -> bool {
         self.data.contains_key(name)
     }
-}
\ No newline at end of file
+}
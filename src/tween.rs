@@ -0,0 +1,188 @@
+// Animates components over time without holding up the battle, the battle
+// has already moved on and the tweens only catch the picture up
+use crate::components::{HealthBar, Position, Rotation, Sprite, Tint};
+
+use raylib::prelude::Color;
+use specs::prelude::*;
+use specs::{Component, VecStorage};
+
+// Seconds since the last frame, written once per frame before dispatching
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeltaTime(pub f32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseOutCubic,
+    // overshoots a little before settling
+    EaseOutBack,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1. - (1. - t) * (1. - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Easing::EaseOutCubic => 1. - (1. - t).powi(3),
+            Easing::EaseOutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.;
+                1. + c3 * (t - 1.).powi(3) + c1 * (t - 1.).powi(2)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenValue {
+    Position(Position, Position),
+    Scale(f32, f32),
+    Rotation(f32, f32),
+    Tint(Color, Color),
+    // the health shown by the bar, the real value is already in `current`
+    HealthFill(f32, f32),
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+fn lerp_u8(from: u8, to: u8, t: f32) -> u8 {
+    lerp(from as f32, to as f32, t).round().clamp(0., 255.) as u8
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+    pub value: TweenValue,
+    pub duration: f32,
+    pub elapsed: f32,
+    pub easing: Easing,
+    // runs once the tween finishes, e.g. deleting a card that was played
+    pub on_complete: Option<fn(Entity, &LazyUpdate)>,
+}
+
+impl Tween {
+    pub fn new(value: TweenValue, duration: f32, easing: Easing) -> Self {
+        Tween {
+            value,
+            duration,
+            elapsed: 0.,
+            easing,
+            on_complete: None,
+        }
+    }
+
+    pub fn then(mut self, on_complete: fn(Entity, &LazyUpdate)) -> Self {
+        self.on_complete = Some(on_complete);
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            1.
+        } else {
+            self.easing.apply(self.elapsed / self.duration)
+        }
+    }
+}
+
+// Every tween running on an entity, one per value being animated
+#[derive(Component, Debug, Default, Clone)]
+#[storage(VecStorage)]
+pub struct Tweens(pub Vec<Tween>);
+
+impl Tweens {
+    // Replaces any tween already animating the same kind of value
+    pub fn start(storage: &mut WriteStorage<Tweens>, ent: Entity, tween: Tween) {
+        if let Ok(entry) = storage.entry(ent) {
+            let tweens = entry.or_insert_with(Tweens::default);
+            tweens
+                .0
+                .retain(|t| std::mem::discriminant(&t.value) != std::mem::discriminant(&tween.value));
+            tweens.0.push(tween);
+        }
+    }
+}
+
+pub fn delete_entity(ent: Entity, lazy: &LazyUpdate) {
+    lazy.exec_mut(move |world| {
+        let _res = world.delete_entity(ent);
+    });
+}
+
+pub struct TweenSys;
+
+impl<'a> System<'a> for TweenSys {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+        WriteStorage<'a, Tweens>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Sprite>,
+        WriteStorage<'a, Rotation>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, HealthBar>,
+    );
+
+    fn run(
+        &mut self,
+        (delta, lazy, entities, mut tweens, mut positions, mut sprites, mut rotations, mut tints, mut health_bars): Self::SystemData,
+    ) {
+        for (ent, Tweens(running)) in (&entities, &mut tweens).join() {
+            for tween in running.iter_mut() {
+                tween.elapsed += delta.0;
+                let t = tween.progress();
+                match tween.value {
+                    TweenValue::Position(from, to) => {
+                        let _res = positions.insert(ent, Position::new(lerp(from.x, to.x, t), lerp(from.y, to.y, t)));
+                    }
+                    TweenValue::Scale(from, to) => {
+                        if let Some(sprite) = sprites.get_mut(ent) {
+                            sprite.scale = lerp(from, to, t);
+                        }
+                    }
+                    TweenValue::Rotation(from, to) => {
+                        let _res = rotations.insert(ent, Rotation(lerp(from, to, t)));
+                    }
+                    TweenValue::Tint(from, to) => {
+                        let color = Color::new(
+                            lerp_u8(from.r, to.r, t),
+                            lerp_u8(from.g, to.g, t),
+                            lerp_u8(from.b, to.b, t),
+                            lerp_u8(from.a, to.a, t),
+                        );
+                        let _res = tints.insert(ent, Tint(color));
+                    }
+                    TweenValue::HealthFill(from, to) => {
+                        if let Some(health_bar) = health_bars.get_mut(ent) {
+                            health_bar.shown = lerp(from, to, t);
+                        }
+                    }
+                }
+                if tween.is_finished() {
+                    if let Some(on_complete) = tween.on_complete {
+                        on_complete(ent, &lazy);
+                    }
+                }
+            }
+            running.retain(|tween| !tween.is_finished());
+        }
+    }
+}